@@ -0,0 +1,81 @@
+use crate::{
+    builtins::Builtin,
+    cell_error::CellError,
+    function_registry::{numbers, Argument, Arity},
+    value::Value,
+};
+
+/// Functions that combine all numbers of their arguments, e.g. SUM(A1:A3, 10), or count their values.
+pub(super) fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::eager("AVG", Arity::at_least(1), avg),
+        Builtin::eager("COUNT", Arity::at_least(1), count),
+        Builtin::eager("COUNTA", Arity::at_least(1), counta),
+        Builtin::eager("COUNTBLANK", Arity::at_least(1), countblank),
+        Builtin::eager("MAX", Arity::at_least(1), max),
+        Builtin::eager("MIN", Arity::at_least(1), min),
+        Builtin::eager("SUM", Arity::at_least(1), sum),
+    ]
+}
+
+fn avg(args: &[Argument]) -> Result<Value, CellError> {
+    let nums = numbers(args)?;
+    if nums.is_empty() {
+        return Err(CellError::division_by_zero(
+            "AVG of no numbers.".to_string(),
+        ));
+    }
+    Ok(Value::Number(nums.iter().sum::<f64>() / nums.len() as f64))
+}
+
+fn count(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(numbers(args)?.len() as f64))
+}
+
+/// COUNTA(value1, [value2], ...) counts the values that are not empty.
+fn counta(args: &[Argument]) -> Result<Value, CellError> {
+    let count = args
+        .iter()
+        .flat_map(|arg| arg.values())
+        .filter(|value| **value != Value::Empty)
+        .count();
+    Ok(Value::Number(count as f64))
+}
+
+/// COUNTBLANK(range1, [range2], ...) counts the empty cells, including those with empty text.
+fn countblank(args: &[Argument]) -> Result<Value, CellError> {
+    let count = args
+        .iter()
+        .flat_map(|arg| arg.values())
+        .filter(|value| matches!(value, Value::Empty) || **value == Value::Text(String::new()))
+        .count();
+    Ok(Value::Number(count as f64))
+}
+
+/// MAX(value1, [value2], ...), 0 if there are no numbers.
+fn max(args: &[Argument]) -> Result<Value, CellError> {
+    let nums = numbers(args)?;
+    Ok(Value::Number(
+        match nums.iter().max_by(|a, b| a.total_cmp(b)) {
+            Some(max) => *max,
+            None => 0.0,
+        },
+    ))
+}
+
+/// MIN(value1, [value2], ...), 0 if there are no numbers.
+fn min(args: &[Argument]) -> Result<Value, CellError> {
+    let nums = numbers(args)?;
+    Ok(Value::Number(
+        match nums.iter().min_by(|a, b| a.total_cmp(b)) {
+            Some(min) => *min,
+            None => 0.0,
+        },
+    ))
+}
+
+fn sum(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(
+        numbers(args)?.iter().fold(0.0, |acc, n| acc + n),
+    ))
+}
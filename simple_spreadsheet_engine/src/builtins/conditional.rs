@@ -0,0 +1,197 @@
+use std::cmp::Ordering;
+
+use crate::{
+    builtins::Builtin,
+    cell_error::{CellError, ErrorKind},
+    function_registry::{Arguments, Arity},
+    value::Value,
+};
+
+/// Functions that pick one of their arguments. Only the arguments that are needed to make the choice
+/// and the chosen argument are evaluated.
+pub(super) fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::lazy("IF", Arity::between(2, 3), if_),
+        Builtin::lazy("IFERROR", Arity::exactly(2), if_error),
+        Builtin::lazy("IFNA", Arity::exactly(2), if_na),
+        Builtin::lazy("IFS", Arity::at_least(2), ifs),
+        Builtin::lazy("SWITCH", Arity::at_least(3), switch),
+    ]
+}
+
+/// The value of the argument at the index.
+fn value(args: &Arguments, index: usize) -> Result<Value, CellError> {
+    args.get(index)?.value().cloned()
+}
+
+/// IF(condition, value_if_true, [value_if_false])
+fn if_(args: &Arguments) -> Result<Value, CellError> {
+    if args.get(0)?.boolean()? {
+        value(args, 1)
+    } else if args.len() > 2 {
+        value(args, 2)
+    } else {
+        Ok(Value::Boolean(false))
+    }
+}
+
+/// IFERROR(value, value_if_error)
+fn if_error(args: &Arguments) -> Result<Value, CellError> {
+    match value(args, 0) {
+        Ok(value) => Ok(value),
+        Err(_) => value(args, 1),
+    }
+}
+
+/// IFNA(value, value_if_na)
+fn if_na(args: &Arguments) -> Result<Value, CellError> {
+    match value(args, 0) {
+        Err(e) if e.kind == ErrorKind::NotAvailable => value(args, 1),
+        result => result,
+    }
+}
+
+/// IFS(condition1, value1, [condition2, value2], ...)
+fn ifs(args: &Arguments) -> Result<Value, CellError> {
+    if !args.len().is_multiple_of(2) {
+        return Err(CellError::value(
+            "IFS expects pairs of conditions and values.".to_string(),
+        ));
+    }
+    for i in (0..args.len()).step_by(2) {
+        if args.get(i)?.boolean()? {
+            return value(args, i + 1);
+        }
+    }
+    Err(CellError::not_available(
+        "None of the conditions of IFS holds.".to_string(),
+    ))
+}
+
+/// SWITCH(expression, case1, value1, [case2, value2], ..., [default])
+fn switch(args: &Arguments) -> Result<Value, CellError> {
+    let expression = value(args, 0)?;
+    for i in (1..args.len() - 1).step_by(2) {
+        if value(args, i)?.compare(&expression) == Ordering::Equal {
+            return value(args, i + 1);
+        }
+    }
+    if args.len().is_multiple_of(2) {
+        value(args, args.len() - 1)
+    } else {
+        Err(CellError::not_available(format!(
+            "No case of SWITCH matches '{}'.",
+            expression
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::resolve;
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
+
+    fn text(t: &str) -> Cell {
+        Cell::Text(t.to_string())
+    }
+
+    #[test]
+    fn evaluates_if() {
+        assert_eq!(
+            resolve(&[
+                "5",
+                "=IF(A1 > 3, \"big\", \"small\")",
+                "=IF(A1 < 3, 1)",
+                "=IF(0, 1, 2)",
+                "=IF(D1, 1)"
+            ]),
+            [
+                Cell::Number(5.0),
+                text("big"),
+                Cell::Boolean(false),
+                Cell::Number(2.0),
+                Cell::Number(1.0)
+            ]
+        );
+        assert_eq!(
+            resolve(&["=IF(\"maybe\", 1, 2)"])[0],
+            Cell::Error(CellError::value("'maybe' is not a boolean.".to_string()))
+        );
+    }
+
+    #[test]
+    fn only_evaluates_chosen_branch() {
+        assert_eq!(
+            resolve(&[
+                "text",
+                "=IF(TRUE, 1, A1 + 1)",
+                "=IF(FALSE, A1 * 2, 3)",
+                "=IF(TRUE, A1 * 2, 3)"
+            ])[1..3],
+            [Cell::Number(1.0), Cell::Number(3.0)]
+        );
+    }
+
+    #[test]
+    fn evaluates_iferror_and_ifna() {
+        assert_eq!(
+            resolve(&[
+                "text",
+                "=IFERROR(A1 + 1, \"Oops!\")",
+                "=IFERROR(A1, 0)",
+                "=IFNA(A1 * 2, 0)",
+                "=IFNA(IFS(FALSE, 1), 0)"
+            ]),
+            [
+                text("text"),
+                text("Oops!"),
+                text("text"),
+                Cell::Error(CellError::value(
+                    "Cell A1 contains text 'text' instead of a number.".to_string()
+                )),
+                Cell::Number(0.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn evaluates_ifs() {
+        assert_eq!(
+            resolve(&[
+                "75",
+                "=IFS(A1 >= 90, \"A\", A1 >= 70, \"B\", TRUE, \"C\")",
+                "=IFS(A1 > 100, 1)",
+                "=IFS(TRUE, 1, FALSE)"
+            ])[1..],
+            [
+                text("B"),
+                Cell::Error(CellError::not_available(
+                    "None of the conditions of IFS holds.".to_string()
+                )),
+                Cell::Error(CellError::value(
+                    "IFS expects pairs of conditions and values.".to_string()
+                ))
+            ]
+        );
+    }
+
+    #[test]
+    fn evaluates_switch() {
+        assert_eq!(
+            resolve(&[
+                "Tea",
+                "=SWITCH(A1, \"coffee\", 1, \"tea\", 2)",
+                "=SWITCH(A1, \"coffee\", 1, 0)",
+                "=SWITCH(A1, \"coffee\", 1)"
+            ])[1..],
+            [
+                Cell::Number(2.0),
+                Cell::Number(0.0),
+                Cell::Error(CellError::not_available(
+                    "No case of SWITCH matches 'Tea'.".to_string()
+                ))
+            ]
+        );
+    }
+}
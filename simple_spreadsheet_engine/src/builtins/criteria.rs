@@ -0,0 +1,379 @@
+use crate::{
+    builtins::Builtin,
+    cell::{parse_boolean, parse_number, parse_percent},
+    cell_error::CellError,
+    date,
+    formula::ComparisonOperator,
+    function_registry::{Argument, Arity},
+    value::Value,
+};
+
+/// Functions that aggregate the cells of a range that meet criteria like ">10" or "food*", e.g.
+/// SUMIF(B2:B9, "food", C2:C9) sums the amounts of column C with the category food in column B.
+pub(super) fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::eager("AVERAGEIF", Arity::between(2, 3), averageif),
+        Builtin::eager("AVERAGEIFS", Arity::at_least(3), averageifs),
+        Builtin::eager("COUNTIF", Arity::exactly(2), countif),
+        Builtin::eager("COUNTIFS", Arity::at_least(2), countifs),
+        Builtin::eager("MAXIFS", Arity::at_least(3), maxifs),
+        Builtin::eager("MINIFS", Arity::at_least(3), minifs),
+        Builtin::eager("SUMIF", Arity::between(2, 3), sumif),
+        Builtin::eager("SUMIFS", Arity::at_least(3), sumifs),
+    ]
+}
+
+/// A condition on the value of a cell. Text criteria may start with a comparison operator, so
+/// ">10" holds for numbers greater than 10, "<>0" for all values except 0 and "apple" or "=apple"
+/// for the text apple. Text is compared case-insensitively and when checking for (in)equality with
+/// text, * matches any characters, ? a single character and ~ escapes the next character.
+#[derive(Debug, PartialEq)]
+struct Criterion {
+    operator: ComparisonOperator,
+    operand: Value,
+}
+
+impl Criterion {
+    fn parse(criterion: &Value) -> Self {
+        let text: &str = match criterion {
+            Value::Text(t) => t,
+            Value::Empty => "",
+            value => {
+                return Criterion {
+                    operator: ComparisonOperator::Equal,
+                    operand: value.clone(),
+                }
+            }
+        };
+
+        let (operator, operand) = [
+            (">=", ComparisonOperator::GreaterThanOrEqual),
+            ("<=", ComparisonOperator::LessThanOrEqual),
+            ("<>", ComparisonOperator::NotEqual),
+            (">", ComparisonOperator::GreaterThan),
+            ("<", ComparisonOperator::LessThan),
+            ("=", ComparisonOperator::Equal),
+        ]
+        .into_iter()
+        .find_map(|(prefix, operator)| Some((operator, text.strip_prefix(prefix)?)))
+        .unwrap_or((ComparisonOperator::Equal, text));
+
+        let operand = if let Some(n) = parse_number(operand.trim()) {
+            Value::Number(n)
+        } else if let Some(n) = parse_percent(operand.trim()).or_else(|| date::parse(operand)) {
+            Value::Number(n)
+        } else if let Some(b) = parse_boolean(operand) {
+            Value::Boolean(b)
+        } else {
+            Value::Text(operand.to_string())
+        };
+        Criterion { operator, operand }
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        match (&self.operator, &self.operand) {
+            (ComparisonOperator::Equal, Value::Text(pattern)) => matches_pattern(pattern, value),
+            (ComparisonOperator::NotEqual, Value::Text(pattern)) => {
+                !matches_pattern(pattern, value)
+            }
+            (operator, operand) => {
+                let is_comparable = match (value, operand) {
+                    (Value::Boolean(_), Value::Boolean(_)) | (Value::Text(_), Value::Text(_)) => {
+                        true
+                    }
+                    _ => value.as_number().is_some() && operand.as_number().is_some(),
+                };
+                if is_comparable {
+                    operator.apply(value.compare(operand))
+                } else {
+                    *operator == ComparisonOperator::NotEqual
+                }
+            }
+        }
+    }
+}
+
+/// Whether the value is text, or an empty cell as empty text, that matches the pattern.
+fn matches_pattern(pattern: &str, value: &Value) -> bool {
+    let text = match value {
+        Value::Empty => "",
+        Value::Text(t) => t,
+        _ => return false,
+    };
+    let pattern: Vec<Wildcard> = {
+        let mut out = Vec::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            out.push(match c {
+                '*' => Wildcard::Any,
+                '?' => Wildcard::One,
+                '~' => Wildcard::Char(chars.next().unwrap_or('~')),
+                c => Wildcard::Char(c),
+            });
+        }
+        out
+    };
+    let text: Vec<char> = text.chars().collect();
+
+    // Match greedily and when that fails, let the last * take one more character.
+    let (mut p, mut t) = (0, 0);
+    let mut last_any: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(Wildcard::Any) => {
+                last_any = Some((p, t));
+                p += 1;
+            }
+            Some(Wildcard::One) => (p, t) = (p + 1, t + 1),
+            Some(Wildcard::Char(c)) if c.to_lowercase().eq(text[t].to_lowercase()) => {
+                (p, t) = (p + 1, t + 1)
+            }
+            _ => match last_any {
+                Some((any, taken)) => {
+                    last_any = Some((any, taken + 1));
+                    (p, t) = (any + 1, taken + 1);
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|w| *w == Wildcard::Any)
+}
+
+#[derive(PartialEq)]
+enum Wildcard {
+    /// Any characters, including none.
+    Any,
+    /// A single character.
+    One,
+    Char(char),
+}
+
+/// The values of `target` at the positions where all ranges of `conditions`, which are pairs of a
+/// range and a criterion, meet their criterion.
+fn select<'a>(
+    name: &str,
+    target: &'a Argument,
+    conditions: &[Argument],
+) -> Result<Vec<&'a Value>, CellError> {
+    if !conditions.len().is_multiple_of(2) {
+        return Err(CellError::value(format!(
+            "{} expects pairs of ranges and criteria.",
+            name
+        )));
+    }
+    let values = target.values();
+    let mut selected = vec![true; values.len()];
+    for condition in conditions.chunks(2) {
+        let range = condition[0].values();
+        if range.len() != values.len() {
+            return Err(CellError::value(format!(
+                "{} expects ranges of the same size but got {} and {} values.",
+                name,
+                values.len(),
+                range.len()
+            )));
+        }
+        let criterion = Criterion::parse(condition[1].value()?);
+        for (selected, value) in selected.iter_mut().zip(range) {
+            *selected = *selected && criterion.matches(value);
+        }
+    }
+    Ok(values
+        .into_iter()
+        .zip(selected)
+        .filter_map(|(value, selected)| selected.then_some(value))
+        .collect())
+}
+
+/// The numbers of the selected values.
+fn numbers(values: Vec<&Value>) -> Vec<f64> {
+    values.into_iter().filter_map(Value::as_number).collect()
+}
+
+fn average(name: &str, values: Vec<&Value>) -> Result<Value, CellError> {
+    let nums = numbers(values);
+    if nums.is_empty() {
+        return Err(CellError::division_by_zero(format!(
+            "{} found no numbers that meet the criteria.",
+            name
+        )));
+    }
+    Ok(Value::Number(
+        nums.iter().fold(0.0, |acc, n| acc + n) / nums.len() as f64,
+    ))
+}
+
+fn sum(values: Vec<&Value>) -> Value {
+    Value::Number(numbers(values).iter().fold(0.0, |acc, n| acc + n))
+}
+
+/// AVERAGEIF(range, criterion, [average_range])
+fn averageif(args: &[Argument]) -> Result<Value, CellError> {
+    let target = args.get(2).unwrap_or(&args[0]);
+    average("AVERAGEIF", select("AVERAGEIF", target, &args[..2])?)
+}
+
+/// AVERAGEIFS(average_range, range1, criterion1, [range2, criterion2], ...)
+fn averageifs(args: &[Argument]) -> Result<Value, CellError> {
+    average("AVERAGEIFS", select("AVERAGEIFS", &args[0], &args[1..])?)
+}
+
+/// COUNTIF(range, criterion)
+fn countif(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(
+        select("COUNTIF", &args[0], args)?.len() as f64
+    ))
+}
+
+/// COUNTIFS(range1, criterion1, [range2, criterion2], ...)
+fn countifs(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(
+        select("COUNTIFS", &args[0], args)?.len() as f64
+    ))
+}
+
+/// MAXIFS(max_range, range1, criterion1, [range2, criterion2], ...), 0 if no number meets the
+/// criteria.
+fn maxifs(args: &[Argument]) -> Result<Value, CellError> {
+    let nums = numbers(select("MAXIFS", &args[0], &args[1..])?);
+    Ok(Value::Number(
+        nums.into_iter()
+            .max_by(|a, b| a.total_cmp(b))
+            .unwrap_or(0.0),
+    ))
+}
+
+/// MINIFS(min_range, range1, criterion1, [range2, criterion2], ...), 0 if no number meets the
+/// criteria.
+fn minifs(args: &[Argument]) -> Result<Value, CellError> {
+    let nums = numbers(select("MINIFS", &args[0], &args[1..])?);
+    Ok(Value::Number(
+        nums.into_iter()
+            .min_by(|a, b| a.total_cmp(b))
+            .unwrap_or(0.0),
+    ))
+}
+
+/// SUMIF(range, criterion, [sum_range])
+fn sumif(args: &[Argument]) -> Result<Value, CellError> {
+    let target = args.get(2).unwrap_or(&args[0]);
+    Ok(sum(select("SUMIF", target, &args[..2])?))
+}
+
+/// SUMIFS(sum_range, range1, criterion1, [range2, criterion2], ...)
+fn sumifs(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(sum(select("SUMIFS", &args[0], &args[1..])?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::criteria::Criterion;
+    use crate::builtins::resolve_rows;
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
+    use crate::csv;
+    use crate::value::Value;
+
+    fn matches(criterion: &str, value: Value) -> bool {
+        Criterion::parse(&Value::Text(criterion.to_string())).matches(&value)
+    }
+
+    fn text(t: &str) -> Value {
+        Value::Text(t.to_string())
+    }
+
+    #[test]
+    fn compares_numbers() {
+        assert!(matches(">10", Value::Number(11.0)));
+        assert!(!matches(">10", Value::Number(10.0)));
+        assert!(matches(">=10", Value::Number(10.0)));
+        assert!(matches("<=10", Value::Number(-1.0)));
+        assert!(matches("10", Value::Number(10.0)));
+        assert!(matches("=10", Value::Number(10.0)));
+        assert!(matches("<>0", Value::Number(1.0)));
+        assert!(matches("<>0", text("zero")));
+        assert!(matches("<>0", Value::Empty));
+        assert!(!matches(">10", text("11")));
+        assert!(!matches("<5", Value::Empty));
+        assert!(matches(">=50%", Value::Number(0.5)));
+        assert!(matches("<2015-06-01", Value::Date(42144.0)));
+        assert!(
+            Criterion::parse(&Value::Number(3.0)).matches(&Value::Number(3.0)),
+            "numbers are compared for equality"
+        );
+    }
+
+    #[test]
+    fn compares_text() {
+        assert!(matches("food", text("Food")));
+        assert!(!matches("food", text("fast food")));
+        assert!(matches("<>food", text("rent")));
+        assert!(matches("<m", text("Apple")));
+        assert!(!matches("<m", Value::Number(1.0)));
+        assert!(matches("TRUE", Value::Boolean(true)));
+        assert!(matches("", Value::Empty));
+        assert!(matches("=", text("")));
+        assert!(!matches("=", text("x")));
+        assert!(matches("<>", text("x")));
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("f*", text("Food")));
+        assert!(matches("*o*d", text("food")));
+        assert!(matches("*", text("")));
+        assert!(!matches("*", Value::Number(1.0)));
+        assert!(matches("?at", text("cat")));
+        assert!(!matches("?at", text("at")));
+        assert!(matches("a*b*c", text("aXbYbZc")));
+        assert!(!matches("a*b*c", text("aXbYbZ")));
+        assert!(matches("why~?", text("why?")));
+        assert!(!matches("why~?", text("whyX")));
+        assert!(matches("<>f*", text("rent")));
+        assert!(!matches("<>f*", text("fuel")));
+    }
+
+    #[test]
+    fn aggregates_cells_that_meet_criteria() {
+        let formulas = [
+            "=SUMIF(A1:A4, \"food\", B1:B4)",
+            "=COUNTIF(B1:B4, \">10\")",
+            "=AVERAGEIF(A1:A4, \"f*\", B1:B4)",
+            "=SUMIFS(B1:B4, A1:A4, \"f*\", B1:B4, \"<50\")",
+            "=COUNTIFS(A1:A4, \"<>rent\", B1:B4, \">=10\")",
+            "=AVERAGEIFS(B1:B4, A1:A4, \"tax\")",
+            "=MAXIFS(B1:B4, A1:A4, \"f*\")",
+            "=MINIFS(B1:B4, A1:A4, \"f*\")",
+            "=SUMIF(A1:A4, \"food\", B1:B3)",
+            "=SUMIFS(B1:B4, A1:A4, \"f*\", B1:B4)",
+        ];
+        let rows: Vec<String> = formulas.iter().map(|f| csv::join(&["", f])).collect();
+        let input = format!("food,12\nrent,800\nFood,8\nfuel,50\n{}", rows.join("\n"));
+        let results: Vec<Cell> = resolve_rows(&input)[4..]
+            .iter()
+            .map(|row| row[1].clone())
+            .collect();
+        assert_eq!(
+            results,
+            [
+                Cell::Number(20.0),
+                Cell::Number(3.0),
+                Cell::Number(70.0 / 3.0),
+                Cell::Number(20.0),
+                Cell::Number(2.0),
+                Cell::Error(CellError::division_by_zero(
+                    "AVERAGEIFS found no numbers that meet the criteria.".to_string()
+                )),
+                Cell::Number(50.0),
+                Cell::Number(8.0),
+                Cell::Error(CellError::value(
+                    "SUMIF expects ranges of the same size but got 3 and 4 values.".to_string()
+                )),
+                Cell::Error(CellError::value(
+                    "SUMIFS expects pairs of ranges and criteria.".to_string()
+                )),
+            ]
+        );
+    }
+}
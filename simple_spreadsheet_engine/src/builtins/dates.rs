@@ -0,0 +1,384 @@
+use crate::{
+    builtins::Builtin,
+    cell_error::CellError,
+    date,
+    function_registry::{Argument, Arguments, Arity},
+    value::Value,
+};
+
+/// Functions that create dates or take them apart.
+pub(super) fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::eager("DATE", Arity::exactly(3), date_),
+        Builtin::eager("DATEDIF", Arity::exactly(3), datedif),
+        Builtin::eager("DAY", Arity::exactly(1), day),
+        Builtin::eager("EDATE", Arity::exactly(2), edate),
+        Builtin::eager("EOMONTH", Arity::exactly(2), eomonth),
+        Builtin::eager("MONTH", Arity::exactly(1), month),
+        Builtin::eager("NETWORKDAYS", Arity::between(2, 3), networkdays),
+        Builtin::lazy("TODAY", Arity::exactly(0), today),
+        Builtin::eager("WEEKDAY", Arity::between(1, 2), weekday),
+        Builtin::eager("YEAR", Arity::exactly(1), year),
+    ]
+}
+
+/// Serial number of a date argument. Numbers are serial numbers and text must be an ISO date.
+pub(super) fn serial(value: &Value) -> Result<f64, CellError> {
+    let serial = match value {
+        Value::Text(t) => {
+            date::parse(t).ok_or_else(|| CellError::value(format!("'{}' is not a date.", t)))?
+        }
+        value => Argument::Value(value.clone()).number()?,
+    };
+    date::check(serial)
+}
+
+fn date_argument(arg: &Argument) -> Result<f64, CellError> {
+    serial(arg.value()?)
+}
+
+/// Add months to a date. The day is kept if that month has it, else it becomes the last day of the
+/// month, e.g. one month after 2015-01-31 is 2015-02-28.
+fn add_months(serial: f64, months: i64) -> Result<(i64, u32, u32), CellError> {
+    let (year, month, day) = date::ymd(serial);
+    let first = (month as i64)
+        .checked_add(months)
+        .and_then(|month| date::checked_serial(year, month, 1))
+        .ok_or_else(date::out_of_range)?;
+    let (year, month, _) = date::ymd(first);
+    Ok((year, month, day.min(date::days_in_month(year, month))))
+}
+
+/// Day of the week of a serial number, from 0 for Monday to 6 for Sunday.
+pub(super) fn day_of_week(serial: f64) -> i64 {
+    // Serial number 2 (1900-01-01) is a Monday.
+    (serial.floor() as i64 - 2).rem_euclid(7)
+}
+
+/// DATE(year, month, day)
+fn date_(args: &[Argument]) -> Result<Value, CellError> {
+    let year = args[0].number()? as i64;
+    let month = args[1].number()? as i64;
+    let day = args[2].number()? as i64;
+    date::checked_serial(year, month, day)
+        .map(Value::Date)
+        .ok_or_else(date::out_of_range)
+}
+
+/// DATEDIF(start_date, end_date, unit), where unit is Y, M or D for the complete years, months or
+/// days in between. MD gives the days without the complete months, YM the months without the
+/// complete years and YD the days without the complete years.
+fn datedif(args: &[Argument]) -> Result<Value, CellError> {
+    let start = date_argument(&args[0])?.floor();
+    let end = date_argument(&args[1])?.floor();
+    if start > end {
+        return Err(CellError::value(
+            "The start date of DATEDIF is after the end date.".to_string(),
+        ));
+    }
+    let (start_year, start_month, start_day) = date::ymd(start);
+    let (end_year, end_month, end_day) = date::ymd(end);
+    let mut months = (end_year - start_year) * 12 + end_month as i64 - start_month as i64;
+    if end_day < start_day {
+        months -= 1;
+    }
+
+    let unit = match args[2].value()? {
+        Value::Text(unit) => unit.to_uppercase(),
+        value => {
+            return Err(CellError::value(format!(
+                "'{}' is not a unit of DATEDIF.",
+                value
+            )))
+        }
+    };
+    let out = match unit.as_str() {
+        "Y" => months / 12,
+        "M" => months,
+        "D" => (end - start) as i64,
+        "YM" => months % 12,
+        "MD" => {
+            let (year, month, day) = add_months(start, months)?;
+            (end - date::serial(year, month as i64, day as i64)) as i64
+        }
+        "YD" => {
+            let (year, month, day) = add_months(start, months / 12 * 12)?;
+            (end - date::serial(year, month as i64, day as i64)) as i64
+        }
+        _ => {
+            return Err(CellError::value(format!(
+                "'{}' is not a unit of DATEDIF.",
+                unit
+            )))
+        }
+    };
+    Ok(Value::Number(out as f64))
+}
+
+/// DAY(date)
+fn day(args: &[Argument]) -> Result<Value, CellError> {
+    let (_, _, day) = date::ymd(date_argument(&args[0])?);
+    Ok(Value::Number(day as f64))
+}
+
+/// EDATE(start_date, months)
+fn edate(args: &[Argument]) -> Result<Value, CellError> {
+    let (year, month, day) = add_months(date_argument(&args[0])?, args[1].number()? as i64)?;
+    Ok(Value::Date(date::serial(year, month as i64, day as i64)))
+}
+
+/// EOMONTH(start_date, months)
+fn eomonth(args: &[Argument]) -> Result<Value, CellError> {
+    let (year, month, _) = add_months(date_argument(&args[0])?, args[1].number()? as i64)?;
+    let day = date::days_in_month(year, month);
+    Ok(Value::Date(date::serial(year, month as i64, day as i64)))
+}
+
+/// MONTH(date)
+fn month(args: &[Argument]) -> Result<Value, CellError> {
+    let (_, month, _) = date::ymd(date_argument(&args[0])?);
+    Ok(Value::Number(month as f64))
+}
+
+/// NETWORKDAYS(start_date, end_date, [holidays]) counts the days from Monday to Friday between both
+/// dates, including both dates, that are not holidays. Negative if the end date comes first.
+fn networkdays(args: &[Argument]) -> Result<Value, CellError> {
+    let start = date_argument(&args[0])?.floor() as i64;
+    let end = date_argument(&args[1])?.floor() as i64;
+    let holidays = match args.get(2) {
+        Some(arg) => arg
+            .values()
+            .into_iter()
+            .filter(|value| **value != Value::Empty)
+            .map(|value| serial(value).map(|serial| serial.floor() as i64))
+            .collect::<Result<Vec<i64>, CellError>>()?,
+        None => vec![],
+    };
+
+    let (first, last) = (start.min(end), start.max(end));
+    let is_working_day = |day: i64| day_of_week(day as f64) < 5;
+    let mut days_off: Vec<i64> = holidays
+        .into_iter()
+        .filter(|&day| (first..=last).contains(&day) && is_working_day(day))
+        .collect();
+    days_off.sort_unstable();
+    days_off.dedup();
+
+    let count =
+        (working_days_before(last + 1) - working_days_before(first) - days_off.len() as i64) as f64;
+    Ok(Value::Number(if start > end { -count } else { count }))
+}
+
+/// Number of days from Monday to Friday before a serial number, counted from serial number 2 (a
+/// Monday) and negative before it.
+fn working_days_before(serial: i64) -> i64 {
+    let days = serial - 2;
+    days.div_euclid(7) * 5 + days.rem_euclid(7).min(5)
+}
+
+/// TODAY(), the date of the evaluation context.
+fn today(args: &Arguments) -> Result<Value, CellError> {
+    Ok(Value::Date(args.context().today))
+}
+
+/// WEEKDAY(date, [type]), where type 1 numbers the days from Sunday = 1 to Saturday = 7, type 2 from
+/// Monday = 1 to Sunday = 7 and type 3 from Monday = 0 to Sunday = 6.
+fn weekday(args: &[Argument]) -> Result<Value, CellError> {
+    let day = day_of_week(date_argument(&args[0])?);
+    let out = match args.get(1).map(|arg| arg.number()).transpose()? {
+        None => (day + 1) % 7 + 1,
+        Some(1.0) => (day + 1) % 7 + 1,
+        Some(2.0) => day + 1,
+        Some(3.0) => day,
+        Some(n) => {
+            return Err(CellError::value(format!(
+                "'{}' is not a type of WEEKDAY.",
+                n
+            )))
+        }
+    };
+    Ok(Value::Number(out as f64))
+}
+
+/// YEAR(date)
+fn year(args: &[Argument]) -> Result<Value, CellError> {
+    let (year, _, _) = date::ymd(date_argument(&args[0])?);
+    Ok(Value::Number(year as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::resolve;
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
+    use crate::date;
+
+    fn date(year: i64, month: i64, day: i64) -> Cell {
+        Cell::Date(date::serial(year, month, day))
+    }
+
+    fn numbers(numbers: &[f64]) -> Vec<Cell> {
+        numbers.iter().map(|n| Cell::Number(*n)).collect()
+    }
+
+    #[test]
+    fn creates_dates() {
+        assert_eq!(
+            resolve(&[
+                "=DATE(2015, 5, 20)",
+                "=DATE(2015, 14, 1)",
+                "=TODAY()",
+                "=TODAY() + 30",
+                "=TODAY() - DATE(2015, 1, 1)",
+            ]),
+            [
+                date(2015, 5, 20),
+                date(2016, 2, 1),
+                date(2015, 5, 20),
+                date(2015, 6, 19),
+                Cell::Number(139.0)
+            ]
+        );
+        let out_of_range = Cell::Error(date::out_of_range());
+        assert_eq!(
+            resolve(&[
+                "=DATE(10 ** 18, 1, 1)",
+                "=DATE(10000, 1, 1)",
+                "=DATE(1899, 12, 29)",
+                "=DATE(2015, 10 ** 18, 1)",
+                "=DATE(9999, 12, 31)",
+                "=TODAY() + 10 ** 300",
+            ]),
+            [
+                out_of_range.clone(),
+                out_of_range.clone(),
+                out_of_range.clone(),
+                out_of_range.clone(),
+                date(9999, 12, 31),
+                out_of_range
+            ]
+        );
+    }
+
+    #[test]
+    fn takes_dates_apart() {
+        assert_eq!(
+            resolve(&[
+                "2016-02-29",
+                "=YEAR(A1)",
+                "=MONTH(A1)",
+                "=DAY(A1)",
+                "=WEEKDAY(A1)",
+                "=WEEKDAY(A1, 2)",
+                "=WEEKDAY(A1, 3)",
+                "=YEAR(\"2015-05-20\")",
+            ])[1..],
+            numbers(&[2016.0, 2.0, 29.0, 2.0, 1.0, 0.0, 2015.0])
+        );
+        let out_of_range = Cell::Error(date::out_of_range());
+        assert_eq!(
+            resolve(&[
+                "=YEAR(10 ** 300)",
+                "=MONTH(-1)",
+                "=DAY(2958466)",
+                "=WEEKDAY(\"1899-12-29\")",
+                "=YEAR(2958465.5)",
+            ]),
+            [
+                out_of_range.clone(),
+                out_of_range.clone(),
+                out_of_range.clone(),
+                out_of_range,
+                Cell::Number(9999.0)
+            ]
+        );
+        assert_eq!(
+            resolve(&["=DAY(\"tomorrow\")"])[0],
+            Cell::Error(CellError::value("'tomorrow' is not a date.".to_string()))
+        );
+    }
+
+    #[test]
+    fn adds_months() {
+        assert_eq!(
+            resolve(&[
+                "2015-01-31",
+                "=EDATE(A1, 1)",
+                "=EDATE(A1, -2)",
+                "=EOMONTH(A1, 1)",
+                "=EOMONTH(A1, 13)",
+            ])[1..],
+            [
+                date(2015, 2, 28),
+                date(2014, 11, 30),
+                date(2015, 2, 28),
+                date(2016, 2, 29)
+            ]
+        );
+        let out_of_range = Cell::Error(date::out_of_range());
+        assert_eq!(
+            resolve(&[
+                "9999-12-15",
+                "=EDATE(A1, 1)",
+                "=EOMONTH(A1, 1)",
+                "=EDATE(DATE(2015, 1, 1), 10 ** 17)",
+                "=EDATE(DATE(1900, 1, 1), -1)",
+                "=EOMONTH(A1, 0)",
+            ])[1..],
+            [
+                out_of_range.clone(),
+                out_of_range.clone(),
+                out_of_range.clone(),
+                out_of_range,
+                date(9999, 12, 31)
+            ]
+        );
+    }
+
+    #[test]
+    fn evaluates_datedif() {
+        assert_eq!(
+            resolve(&[
+                "2014-03-15",
+                "2016-02-10",
+                "=DATEDIF(A1, B1, \"Y\")",
+                "=DATEDIF(A1, B1, \"M\")",
+                "=DATEDIF(A1, B1, \"D\")",
+                "=DATEDIF(A1, B1, \"YM\")",
+                "=DATEDIF(A1, B1, \"MD\")",
+                "=DATEDIF(A1, B1, \"YD\")",
+            ])[2..],
+            numbers(&[1.0, 22.0, 697.0, 10.0, 26.0, 332.0])
+        );
+        assert_eq!(
+            resolve(&["=DATEDIF(TODAY(), DATE(2015, 1, 1), \"D\")"])[0],
+            Cell::Error(CellError::value(
+                "The start date of DATEDIF is after the end date.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn counts_working_days() {
+        assert_eq!(
+            resolve(&[
+                "2015-05-01",
+                "2015-05-31",
+                "2015-05-25",
+                "=NETWORKDAYS(A1, B1)",
+                "=NETWORKDAYS(A1, B1, C1)",
+                "=NETWORKDAYS(B1, A1, C1:C1)",
+                "=NETWORKDAYS(\"2015-05-16\", \"2015-05-17\")",
+                "=NETWORKDAYS(A1, B1, C1:D1)",
+                "=NETWORKDAYS(A1, A1)",
+                "=NETWORKDAYS(0, 9)",
+                "=NETWORKDAYS(0, 2958465)",
+            ])[3..],
+            numbers(&[21.0, 20.0, -20.0, 0.0, 20.0, 1.0, 6.0, 2113190.0])
+        );
+        assert_eq!(
+            resolve(&["=NETWORKDAYS(0, 10 ** 12)"])[0],
+            Cell::Error(date::out_of_range())
+        );
+    }
+}
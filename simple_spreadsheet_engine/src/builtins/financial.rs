@@ -0,0 +1,421 @@
+use crate::{
+    builtins::Builtin,
+    cell_error::CellError,
+    function_registry::{numbers, Argument, Arity},
+    value::Value,
+};
+
+/// Time value of money functions. Like in spreadsheets, money paid out is negative and money
+/// received is positive, and the optional type is 0 for payments at the end of each period or 1 for
+/// payments at the start.
+pub(super) fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::eager("FV", Arity::between(3, 5), fv),
+        Builtin::eager("IPMT", Arity::between(4, 6), ipmt),
+        Builtin::eager("IRR", Arity::between(1, 2), irr),
+        Builtin::eager("NPER", Arity::between(3, 5), nper),
+        Builtin::eager("NPV", Arity::at_least(2), npv),
+        Builtin::eager("PMT", Arity::between(3, 5), pmt),
+        Builtin::eager("PPMT", Arity::between(4, 6), ppmt),
+        Builtin::eager("PV", Arity::between(3, 5), pv),
+        Builtin::eager("RATE", Arity::between(3, 6), rate),
+        Builtin::eager("XIRR", Arity::between(2, 3), xirr),
+        Builtin::eager("XNPV", Arity::exactly(3), xnpv),
+    ]
+}
+
+/// Maximum number of steps of the solver before it gives up.
+const MAX_ITERATIONS: usize = 100;
+
+/// The solver stops when a step changes the rate by less than this.
+const TOLERANCE: f64 = 1e-10;
+
+/// The optional number argument at the index.
+fn optional(args: &[Argument], index: usize, default: f64) -> Result<f64, CellError> {
+    match args.get(index) {
+        Some(arg) => arg.number(),
+        None => Ok(default),
+    }
+}
+
+/// The optional payment type argument at the index, 0 if left out.
+fn payment_type(args: &[Argument], index: usize) -> Result<f64, CellError> {
+    Ok(if optional(args, index, 0.0)? == 0.0 {
+        0.0
+    } else {
+        1.0
+    })
+}
+
+/// Future value of the present value and the payments.
+fn future_value(rate: f64, nper: f64, pmt: f64, pv: f64, type_: f64) -> f64 {
+    if rate == 0.0 {
+        return -(pv + pmt * nper);
+    }
+    let growth = (1.0 + rate).powf(nper);
+    -(pv * growth + pmt * (1.0 + rate * type_) * (growth - 1.0) / rate)
+}
+
+/// Payment per period that pays off the present value down to the future value.
+fn payment(rate: f64, nper: f64, pv: f64, fv: f64, type_: f64) -> f64 {
+    if rate == 0.0 {
+        return -(pv + fv) / nper;
+    }
+    let growth = (1.0 + rate).powf(nper);
+    -rate * (fv + pv * growth) / ((1.0 + rate * type_) * (growth - 1.0))
+}
+
+/// Interest part of the payment in the period `per`, where 1 is the first period.
+fn interest_payment(args: &[Argument]) -> Result<(f64, f64), CellError> {
+    let rate = args[0].number()?;
+    let per = args[1].number()?;
+    let nper = args[2].number()?;
+    let pv = args[3].number()?;
+    let fv = optional(args, 4, 0.0)?;
+    let type_ = payment_type(args, 5)?;
+    if per < 1.0 || per > nper {
+        return Err(CellError::num(format!(
+            "The period {} lies outside of the periods 1 to {}.",
+            per, nper
+        )));
+    }
+    let pmt = payment(rate, nper, pv, fv, type_);
+    // The interest is paid on the balance after the previous period.
+    let interest = future_value(rate, per - 1.0, pmt, pv, type_) * rate;
+    let interest = if type_ == 0.0 {
+        interest
+    } else if per == 1.0 {
+        // Payments at the start of the first period pay no interest yet.
+        0.0
+    } else {
+        interest / (1.0 + rate)
+    };
+    Ok((pmt, interest))
+}
+
+/// Find the rate where `f` is 0 with Newton's method, starting at the guess. Rates must stay above
+/// -100%, so a step that goes below that only goes halfway.
+fn solve(name: &str, guess: f64, f: impl Fn(f64) -> f64) -> Result<f64, CellError> {
+    let mut rate = guess;
+    for _ in 0..MAX_ITERATIONS {
+        let step = 1e-6 * rate.abs().max(1.0);
+        let slope = (f(rate + step) - f(rate - step)) / (2.0 * step);
+        let mut next = rate - f(rate) / slope;
+        if !next.is_finite() {
+            break;
+        }
+        if next <= -1.0 {
+            next = (rate - 1.0) / 2.0;
+        }
+        // A rate of -100% loses all money, so getting stuck close to it is no solution.
+        if (next - rate).abs() < TOLERANCE && next > -1.0 + 1e-6 {
+            return Ok(next);
+        }
+        rate = next;
+    }
+    Err(CellError::num(format!(
+        "{} did not converge. Try another guess.",
+        name
+    )))
+}
+
+/// Check that the cash flows have both an investment and a return, else there is no rate of return.
+fn expect_cash_flows(name: &str, values: &[f64]) -> Result<(), CellError> {
+    if values.iter().any(|v| *v > 0.0) && values.iter().any(|v| *v < 0.0) {
+        return Ok(());
+    }
+    Err(CellError::num(format!(
+        "{} expects at least one positive and one negative cash flow.",
+        name
+    )))
+}
+
+/// Cash flows and their dates for XNPV and XIRR, which must be numbers at the same positions.
+fn dated_cash_flows(
+    name: &str,
+    values: &Argument,
+    dates: &Argument,
+) -> Result<Vec<(f64, f64)>, CellError> {
+    let (values, dates) = (values.values(), dates.values());
+    if values.len() != dates.len() {
+        return Err(CellError::value(format!(
+            "{} expects as many dates as cash flows but got {} and {}.",
+            name,
+            dates.len(),
+            values.len()
+        )));
+    }
+    values
+        .iter()
+        .zip(dates)
+        .map(
+            |(value, date)| match (value.as_number(), date.as_number()) {
+                (Some(value), Some(date)) => Ok((value, date.floor())),
+                _ => Err(CellError::value(format!(
+                    "{} expects a number and a date but got '{}' and '{}'.",
+                    name, value, date
+                ))),
+            },
+        )
+        .collect()
+}
+
+/// Net present value of cash flows on dates, discounted to the date of the first cash flow.
+fn net_present_value(rate: f64, cash_flows: &[(f64, f64)]) -> f64 {
+    let start = cash_flows[0].1;
+    cash_flows
+        .iter()
+        .map(|(value, date)| value / (1.0 + rate).powf((date - start) / 365.0))
+        .fold(0.0, |acc, n| acc + n)
+}
+
+/// FV(rate, nper, pmt, [pv], [type])
+fn fv(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(future_value(
+        args[0].number()?,
+        args[1].number()?,
+        args[2].number()?,
+        optional(args, 3, 0.0)?,
+        payment_type(args, 4)?,
+    )))
+}
+
+/// IPMT(rate, per, nper, pv, [fv], [type]), the interest part of the payment in a period.
+fn ipmt(args: &[Argument]) -> Result<Value, CellError> {
+    let (_, interest) = interest_payment(args)?;
+    Ok(Value::Number(interest))
+}
+
+/// IRR(values, [guess]), the rate of return of cash flows at regular periods.
+fn irr(args: &[Argument]) -> Result<Value, CellError> {
+    let values = numbers(&args[..1])?;
+    expect_cash_flows("IRR", &values)?;
+    let guess = optional(args, 1, 0.1)?;
+    let rate = solve("IRR", guess, |rate| {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| value / (1.0 + rate).powi(i as i32))
+            .fold(0.0, |acc, n| acc + n)
+    })?;
+    Ok(Value::Number(rate))
+}
+
+/// NPER(rate, pmt, pv, [fv], [type]), the number of periods to pay off the present value.
+fn nper(args: &[Argument]) -> Result<Value, CellError> {
+    let rate = args[0].number()?;
+    let pmt = args[1].number()?;
+    let pv = args[2].number()?;
+    let fv = optional(args, 3, 0.0)?;
+    let type_ = payment_type(args, 4)?;
+    let nper = if rate == 0.0 {
+        -(pv + fv) / pmt
+    } else {
+        let pmt = pmt * (1.0 + rate * type_);
+        ((pmt - fv * rate) / (pmt + pv * rate)).ln() / (1.0 + rate).ln()
+    };
+    if !nper.is_finite() {
+        return Err(CellError::num(
+            "The payments never pay off the present value.".to_string(),
+        ));
+    }
+    Ok(Value::Number(nper))
+}
+
+/// NPV(rate, value1, [value2], ...), the net present value of cash flows at the end of each period.
+fn npv(args: &[Argument]) -> Result<Value, CellError> {
+    let rate = args[0].number()?;
+    let npv = numbers(&args[1..])?
+        .iter()
+        .enumerate()
+        .map(|(i, value)| value / (1.0 + rate).powi(i as i32 + 1))
+        .fold(0.0, |acc, n| acc + n);
+    Ok(Value::Number(npv))
+}
+
+/// PMT(rate, nper, pv, [fv], [type])
+fn pmt(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(payment(
+        args[0].number()?,
+        args[1].number()?,
+        args[2].number()?,
+        optional(args, 3, 0.0)?,
+        payment_type(args, 4)?,
+    )))
+}
+
+/// PPMT(rate, per, nper, pv, [fv], [type]), the principal part of the payment in a period.
+fn ppmt(args: &[Argument]) -> Result<Value, CellError> {
+    let (pmt, interest) = interest_payment(args)?;
+    Ok(Value::Number(pmt - interest))
+}
+
+/// PV(rate, nper, pmt, [fv], [type])
+fn pv(args: &[Argument]) -> Result<Value, CellError> {
+    let rate = args[0].number()?;
+    let nper = args[1].number()?;
+    let pmt = args[2].number()?;
+    let fv = optional(args, 3, 0.0)?;
+    let type_ = payment_type(args, 4)?;
+    let pv = if rate == 0.0 {
+        -(fv + pmt * nper)
+    } else {
+        let growth = (1.0 + rate).powf(nper);
+        -(fv + pmt * (1.0 + rate * type_) * (growth - 1.0) / rate) / growth
+    };
+    Ok(Value::Number(pv))
+}
+
+/// RATE(nper, pmt, pv, [fv], [type], [guess]), the interest rate per period.
+fn rate(args: &[Argument]) -> Result<Value, CellError> {
+    let nper = args[0].number()?;
+    let pmt = args[1].number()?;
+    let pv = args[2].number()?;
+    let fv = optional(args, 3, 0.0)?;
+    let type_ = payment_type(args, 4)?;
+    let guess = optional(args, 5, 0.1)?;
+    let rate = solve("RATE", guess, |rate| {
+        future_value(rate, nper, pmt, pv, type_) - fv
+    })?;
+    Ok(Value::Number(rate))
+}
+
+/// XIRR(values, dates, [guess]), the yearly rate of return of cash flows on any dates.
+fn xirr(args: &[Argument]) -> Result<Value, CellError> {
+    let cash_flows = dated_cash_flows("XIRR", &args[0], &args[1])?;
+    let values: Vec<f64> = cash_flows.iter().map(|(value, _)| *value).collect();
+    expect_cash_flows("XIRR", &values)?;
+    let guess = optional(args, 2, 0.1)?;
+    let rate = solve("XIRR", guess, |rate| net_present_value(rate, &cash_flows))?;
+    Ok(Value::Number(rate))
+}
+
+/// XNPV(rate, values, dates), the net present value of cash flows on any dates.
+fn xnpv(args: &[Argument]) -> Result<Value, CellError> {
+    let rate = args[0].number()?;
+    let cash_flows = dated_cash_flows("XNPV", &args[1], &args[2])?;
+    if cash_flows.is_empty() {
+        return Err(CellError::num(
+            "XNPV expects at least one cash flow.".to_string(),
+        ));
+    }
+    Ok(Value::Number(net_present_value(rate, &cash_flows)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins;
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
+
+    /// Resolve a single row of cells and round the numbers to cents, or to 6 decimals for rates.
+    fn resolve(cells: &[&str], decimals: i32) -> Vec<Cell> {
+        let scale = 10f64.powi(decimals);
+        builtins::resolve(cells)
+            .into_iter()
+            .map(|cell| match cell {
+                Cell::Number(n) => Cell::Number((n * scale).round() / scale),
+                cell => cell,
+            })
+            .collect()
+    }
+
+    fn numbers(numbers: &[f64]) -> Vec<Cell> {
+        numbers.iter().map(|n| Cell::Number(*n)).collect()
+    }
+
+    #[test]
+    fn computes_loans() {
+        // A loan of 200000 over 30 years at 6% a year, paid monthly.
+        assert_eq!(
+            resolve(
+                &[
+                    "=PMT(0.5%, 360, 200000)",
+                    "=IPMT(0.5%, 1, 360, 200000)",
+                    "=PPMT(0.5%, 1, 360, 200000)",
+                    "=PV(0.5%, 360, -1199.10)",
+                    "=FV(0.5%, 360, -1199.10, 200000)",
+                    "=NPER(0.5%, -1199.10, 200000)",
+                    "=PMT(0, 10, 1000)",
+                    "=PMT(1%, 12, 1000, 0, 1)",
+                    "=IPMT(1%, 1, 12, 1000, 0, 1)",
+                ],
+                2
+            ),
+            numbers(&[-1199.1, -1000.0, -199.1, 199999.82, -1.06, 360.0, -100.0, -87.97, 0.0])
+        );
+    }
+
+    #[test]
+    fn solves_rates() {
+        assert_eq!(
+            resolve(
+                &[
+                    "-1000",
+                    "300",
+                    "400",
+                    "500",
+                    "=IRR(A1:D1)",
+                    "=NPV(10%, B1:D1) + A1",
+                    "=RATE(360, -1199.10, 200000)",
+                ],
+                6
+            )[4..],
+            numbers(&[0.088963, -21.036814, 0.005])
+        );
+    }
+
+    #[test]
+    fn discounts_dated_cash_flows() {
+        assert_eq!(
+            resolve(
+                &[
+                    "-1000",
+                    "1100",
+                    "2015-01-01",
+                    "2016-01-01",
+                    "=XIRR(A1:B1, C1:D1)",
+                    "=XNPV(10%, A1:B1, C1:D1)",
+                    "=XNPV(10%, A1:B1, C1:C1)",
+                ],
+                6
+            )[4..],
+            [
+                Cell::Number(0.1),
+                Cell::Number(0.0),
+                Cell::Error(CellError::value(
+                    "XNPV expects as many dates as cash flows but got 1 and 2.".to_string()
+                ))
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_errors_instead_of_nan() {
+        assert_eq!(
+            resolve(
+                &[
+                    "=IRR(100, 200)",
+                    "=RATE(10, 100, 1000)",
+                    "=NPER(1%, -1, 1000)",
+                    "=IPMT(1%, 13, 12, 1000)",
+                ],
+                6
+            ),
+            [
+                Cell::Error(CellError::num(
+                    "IRR expects at least one positive and one negative cash flow.".to_string()
+                )),
+                Cell::Error(CellError::num(
+                    "RATE did not converge. Try another guess.".to_string()
+                )),
+                Cell::Error(CellError::num(
+                    "The payments never pay off the present value.".to_string()
+                )),
+                Cell::Error(CellError::num(
+                    "The period 13 lies outside of the periods 1 to 12.".to_string()
+                )),
+            ]
+        );
+    }
+}
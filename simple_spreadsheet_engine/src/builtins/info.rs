@@ -0,0 +1,111 @@
+use crate::{
+    builtins::Builtin,
+    cell_error::{CellError, ErrorKind},
+    function_registry::{Arguments, Arity},
+    value::Value,
+};
+
+/// Functions that tell what kind of error their argument is, instead of propagating it.
+pub(super) fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::lazy("ERROR.TYPE", Arity::exactly(1), error_type),
+        Builtin::lazy("ISERROR", Arity::exactly(1), is_error),
+        Builtin::lazy("ISNA", Arity::exactly(1), is_na),
+    ]
+}
+
+/// The error the argument evaluates to, if any.
+fn error(args: &Arguments) -> Result<Option<CellError>, CellError> {
+    match args.get(0) {
+        Ok(arg) => arg.value().map(|_| None),
+        Err(e) => Ok(Some(e)),
+    }
+}
+
+/// ERROR.TYPE(value), the number of the kind of error, e.g. 2 for #DIV/0!.
+fn error_type(args: &Arguments) -> Result<Value, CellError> {
+    match error(args)? {
+        Some(e) => match e.kind.code() {
+            Some(code) => Ok(Value::Number(code as f64)),
+            None => Err(CellError::not_available(format!(
+                "ERROR.TYPE has no number for {}.",
+                e.kind.symbol()
+            ))),
+        },
+        None => Err(CellError::not_available(
+            "ERROR.TYPE expects an error.".to_string(),
+        )),
+    }
+}
+
+/// ISERROR(value)
+fn is_error(args: &Arguments) -> Result<Value, CellError> {
+    Ok(Value::Boolean(error(args)?.is_some()))
+}
+
+/// ISNA(value)
+fn is_na(args: &Arguments) -> Result<Value, CellError> {
+    let is_na = error(args)?.is_some_and(|e| e.kind == ErrorKind::NotAvailable);
+    Ok(Value::Boolean(is_na))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::resolve;
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
+
+    #[test]
+    fn tests_for_errors() {
+        assert_eq!(
+            resolve(&[
+                "=1 / 0",
+                "=ISERROR(A1)",
+                "=ISNA(A1)",
+                "=ISNA(IFS(FALSE, 1))",
+                "=ISERROR(\"text\")",
+                "=ISERROR(SUM(A1, 1))",
+            ])[1..],
+            [
+                Cell::Boolean(true),
+                Cell::Boolean(false),
+                Cell::Boolean(true),
+                Cell::Boolean(false),
+                Cell::Boolean(true),
+            ]
+        );
+        assert_eq!(
+            resolve(&["=ISERROR(OOPS(1))", "=IFERROR(OOPS(1) + 1, 0)"]),
+            [Cell::Boolean(true), Cell::Number(0.0)]
+        );
+    }
+
+    #[test]
+    fn gives_error_types() {
+        assert_eq!(
+            resolve(&[
+                "=ERROR.TYPE(1 / 0)",
+                "=ERROR.TYPE(1 + \"a\")",
+                "=ERROR.TYPE(OOPS(1))",
+                "=ERROR.TYPE(SQRT(-1))",
+                "=ERROR.TYPE(IFS(FALSE, 1))",
+                "=ERROR.TYPE(1)",
+                "=ERROR.TYPE(H1)",
+                "=H1",
+            ])[..7],
+            [
+                Cell::Number(2.0),
+                Cell::Number(3.0),
+                Cell::Number(5.0),
+                Cell::Number(6.0),
+                Cell::Number(7.0),
+                Cell::Error(CellError::not_available(
+                    "ERROR.TYPE expects an error.".to_string()
+                )),
+                Cell::Error(CellError::not_available(
+                    "ERROR.TYPE has no number for #CYCLE!.".to_string()
+                )),
+            ]
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use std::cmp::Ordering;
+use std::mem::discriminant;
+
+use crate::{
+    builtins::{position, Builtin},
+    cell_error::CellError,
+    function_registry::{Argument, Arity},
+    value::Value,
+};
+
+/// Functions that find a value in a cell range.
+pub(super) fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::eager("HLOOKUP", Arity::between(3, 4), hlookup),
+        Builtin::eager("INDEX", Arity::between(2, 3), index),
+        Builtin::eager("LOOKUP", Arity::between(2, 3), lookup),
+        Builtin::eager("MATCH", Arity::between(2, 3), match_),
+        Builtin::eager("VLOOKUP", Arity::between(3, 4), vlookup),
+        Builtin::eager("XLOOKUP", Arity::between(3, 6), xlookup),
+    ]
+}
+
+/// How a lookup value is matched against the values in a range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MatchMode {
+    Exact,
+    /// The exact value, or else the largest value that is smaller.
+    ExactOrSmaller,
+    /// The exact value, or else the smallest value that is larger.
+    ExactOrLarger,
+}
+
+/// Position of the value that matches `lookup` best. Only values of the same type as the lookup
+/// value are considered, so a number never matches text, but dates count as numbers. Exact matches
+/// of text ignore case.
+fn find(lookup: &Value, values: &[&Value], mode: MatchMode, reverse: bool) -> Option<usize> {
+    let is_number = |value: &Value| matches!(value, Value::Date(_) | Value::Number(_));
+    let mut positions: Vec<usize> = (0..values.len())
+        .filter(|&i| {
+            discriminant(values[i]) == discriminant(lookup)
+                || is_number(values[i]) && is_number(lookup)
+        })
+        .collect();
+    if reverse {
+        positions.reverse();
+    }
+
+    let mut best: Option<usize> = None;
+    for i in positions {
+        let ordering = values[i].compare(lookup);
+        let is_candidate = match (mode, ordering) {
+            (_, Ordering::Equal) => return Some(i),
+            (MatchMode::ExactOrSmaller, Ordering::Less) => true,
+            (MatchMode::ExactOrLarger, Ordering::Greater) => true,
+            _ => false,
+        };
+        let is_better = |best: usize| match mode {
+            MatchMode::ExactOrSmaller => values[i].compare(values[best]) == Ordering::Greater,
+            _ => values[i].compare(values[best]) == Ordering::Less,
+        };
+        if is_candidate && best.is_none_or(is_better) {
+            best = Some(i);
+        }
+    }
+    best
+}
+
+fn not_found(lookup: &Value) -> CellError {
+    CellError::not_available(format!("Could not find '{}'.", lookup))
+}
+
+/// The rows of a range argument. Any other argument is a range of a single cell.
+fn table(arg: &Argument) -> Result<Vec<Vec<&Value>>, CellError> {
+    match arg {
+        Argument::Value(value) => Ok(vec![vec![value]]),
+        Argument::Reference(rows) if rows.first().is_none_or(|row| row.is_empty()) => Err(
+            CellError::reference("The range contains no cells.".to_string()),
+        ),
+        Argument::Reference(rows) => Ok(rows.iter().map(|row| row.iter().collect()).collect()),
+    }
+}
+
+/// The values of a range argument that is a single row or column.
+fn vector(arg: &Argument) -> Result<Vec<&Value>, CellError> {
+    let rows = table(arg)?;
+    if rows.len() > 1 && rows[0].len() > 1 {
+        return Err(CellError::value(
+            "Expected a single row or column but got a block of cells.".to_string(),
+        ));
+    }
+    Ok(arg.values())
+}
+
+/// Cell of a table at a zero-based position.
+fn cell<'a>(rows: &[Vec<&'a Value>], row: usize, col: usize) -> Result<&'a Value, CellError> {
+    match rows.get(row).and_then(|cells| cells.get(col)) {
+        Some(value) => Ok(value),
+        None => Err(CellError::value(format!(
+            "Row {} column {} lies outside of the range.",
+            row + 1,
+            col + 1
+        ))),
+    }
+}
+
+/// Match mode of the optional argument of VLOOKUP and HLOOKUP, approximate by default.
+fn approximate(args: &[Argument], index: usize) -> Result<MatchMode, CellError> {
+    match args.get(index) {
+        Some(arg) if !arg.boolean()? => Ok(MatchMode::Exact),
+        _ => Ok(MatchMode::ExactOrSmaller),
+    }
+}
+
+/// HLOOKUP(lookup_value, table, row_index, [approximate_match])
+fn hlookup(args: &[Argument]) -> Result<Value, CellError> {
+    let lookup = args[0].value()?;
+    let rows = table(&args[1])?;
+    let row = position(&args[2], "Row index")?;
+    match find(lookup, &rows[0], approximate(args, 3)?, false) {
+        Some(col) => cell(&rows, row - 1, col).cloned(),
+        None => Err(not_found(lookup)),
+    }
+}
+
+/// INDEX(range, row, [col])
+fn index(args: &[Argument]) -> Result<Value, CellError> {
+    let rows = table(&args[0])?;
+    let first = position(&args[1], "Row")?;
+    match args.get(2) {
+        Some(arg) => cell(&rows, first - 1, position(arg, "Column")? - 1).cloned(),
+        // A single position counts along a single row.
+        None if rows.len() == 1 => cell(&rows, 0, first - 1).cloned(),
+        None => cell(&rows, first - 1, 0).cloned(),
+    }
+}
+
+/// LOOKUP(lookup_value, lookup_range, [result_range])
+fn lookup(args: &[Argument]) -> Result<Value, CellError> {
+    let lookup = args[0].value()?;
+    let (keys, results) = match args.get(2) {
+        Some(results) => (vector(&args[1])?, vector(results)?),
+        None => {
+            // Search the first row or column of a block, and return from the last one.
+            let rows = table(&args[1])?;
+            if rows[0].len() > rows.len() {
+                (rows[0].clone(), rows[rows.len() - 1].clone())
+            } else {
+                let first = rows.iter().map(|row| row[0]).collect();
+                let last = rows.iter().map(|row| row[row.len() - 1]).collect();
+                (first, last)
+            }
+        }
+    };
+    match find(lookup, &keys, MatchMode::ExactOrSmaller, false) {
+        Some(i) => results
+            .get(i)
+            .map(|value| (*value).clone())
+            .ok_or_else(|| not_found(lookup)),
+        None => Err(not_found(lookup)),
+    }
+}
+
+/// MATCH(lookup_value, lookup_range, [match_type]), where match_type 1 finds the largest value that
+/// is not larger, 0 the exact value and -1 the smallest value that is not smaller.
+fn match_(args: &[Argument]) -> Result<Value, CellError> {
+    let lookup = args[0].value()?;
+    let values = vector(&args[1])?;
+    let mode = match args.get(2).map(|arg| arg.number()).transpose()? {
+        None => MatchMode::ExactOrSmaller,
+        Some(n) if n > 0.0 => MatchMode::ExactOrSmaller,
+        Some(n) if n < 0.0 => MatchMode::ExactOrLarger,
+        Some(_) => MatchMode::Exact,
+    };
+    match find(lookup, &values, mode, false) {
+        Some(i) => Ok(Value::Number((i + 1) as f64)),
+        None => Err(not_found(lookup)),
+    }
+}
+
+/// VLOOKUP(lookup_value, table, column_index, [approximate_match])
+fn vlookup(args: &[Argument]) -> Result<Value, CellError> {
+    let lookup = args[0].value()?;
+    let rows = table(&args[1])?;
+    let col = position(&args[2], "Column index")?;
+    let keys: Vec<&Value> = rows.iter().map(|row| row[0]).collect();
+    match find(lookup, &keys, approximate(args, 3)?, false) {
+        Some(row) => cell(&rows, row, col - 1).cloned(),
+        None => Err(not_found(lookup)),
+    }
+}
+
+/// XLOOKUP(lookup_value, lookup_range, result_range, [if_not_found], [match_mode], [search_mode]),
+/// where match_mode 0 finds the exact value, -1 the exact or next smaller and 1 the exact or next
+/// larger value, and a negative search_mode searches from last to first.
+fn xlookup(args: &[Argument]) -> Result<Value, CellError> {
+    let lookup = args[0].value()?;
+    let keys = vector(&args[1])?;
+    let results = vector(&args[2])?;
+    if keys.len() != results.len() {
+        return Err(CellError::value(
+            "The lookup and result ranges must have the same size.".to_string(),
+        ));
+    }
+    let mode = match args.get(4).map(|arg| arg.number()).transpose()? {
+        None => MatchMode::Exact,
+        Some(n) if n < 0.0 => MatchMode::ExactOrSmaller,
+        Some(n) if n > 0.0 => MatchMode::ExactOrLarger,
+        Some(_) => MatchMode::Exact,
+    };
+    let reverse = match args.get(5) {
+        Some(arg) => arg.number()? < 0.0,
+        None => false,
+    };
+    match (find(lookup, &keys, mode, reverse), args.get(3)) {
+        (Some(i), _) => Ok(results[i].clone()),
+        (None, Some(if_not_found)) => if_not_found.value().cloned(),
+        (None, None) => Err(not_found(lookup)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::lookup::{hlookup, index, lookup, vlookup};
+    use crate::builtins::resolve_rows;
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
+    use crate::csv;
+    use crate::function_registry::Argument;
+    use crate::value::Value;
+
+    /// A price list with the formulas to test in the last row.
+    fn resolve(formulas: &[&str]) -> Vec<Cell> {
+        let input = format!(
+            "Coffee, 2.5, 10\nCookie, 1.2, 20\nTea, 2, 30\nWater, 1, 40\n{}",
+            csv::join(formulas)
+        );
+        resolve_rows(&input).remove(4)
+    }
+
+    fn text(t: &str) -> Cell {
+        Cell::Text(t.to_string())
+    }
+
+    fn not_found(lookup: &str) -> Cell {
+        Cell::Error(CellError::not_available(format!(
+            "Could not find '{}'.",
+            lookup
+        )))
+    }
+
+    #[test]
+    fn evaluates_vlookup() {
+        assert_eq!(
+            resolve(&[
+                "=VLOOKUP(\"tea\", A1:C4, 2, FALSE)",
+                "=VLOOKUP(\"Pie\", A1:C4, 3)",
+                "=VLOOKUP(\"Pie\", A1:C4, 3, FALSE)",
+                "=VLOOKUP(\"Tea\", A1:C4, 4, FALSE)",
+            ]),
+            [
+                Cell::Number(2.0),
+                Cell::Number(20.0),
+                not_found("Pie"),
+                Cell::Error(CellError::value(
+                    "Row 3 column 4 lies outside of the range.".to_string()
+                ))
+            ]
+        );
+    }
+
+    #[test]
+    fn evaluates_hlookup() {
+        assert_eq!(
+            resolve(&["=HLOOKUP(1.2, A2:C3, 2, FALSE)", "=HLOOKUP(5, B1:C4, 4)"]),
+            [Cell::Number(2.0), Cell::Number(1.0)]
+        );
+    }
+
+    #[test]
+    fn evaluates_lookup() {
+        assert_eq!(
+            resolve(&[
+                "=LOOKUP(25, C1:C4, A1:A4)",
+                "=LOOKUP(5, C1:C4, A1:A4)",
+                "=LOOKUP(\"Tea\", A1:C4)",
+            ]),
+            [text("Cookie"), not_found("5"), Cell::Number(30.0)]
+        );
+    }
+
+    #[test]
+    fn matches_numbers_and_dates() {
+        let formulas = csv::join(&[
+            "=VLOOKUP(42145, A1:B2, 2)",
+            "=MATCH(DATE(2015, 5, 20), A1:A2, 0)",
+            "=XLOOKUP(42144.5, A1:A2, B1:B2, \"none\", -1)",
+            "=LOOKUP(DATE(2015, 5, 21), A1:A2, B1:B2)",
+        ]);
+        assert_eq!(
+            resolve_rows(&format!("2015-05-20, a\n2015-05-21, b\n{}", formulas)).remove(2),
+            [text("b"), Cell::Number(1.0), text("a"), text("b")]
+        );
+    }
+
+    #[test]
+    fn evaluates_reversed_ranges() {
+        assert_eq!(
+            resolve(&[
+                "=VLOOKUP(\"Tea\", C4:A1, 3)",
+                "=HLOOKUP(\"Coffee\", C3:A1, 3)",
+                "=LOOKUP(\"Tea\", C4:A1)",
+            ]),
+            [Cell::Number(30.0), text("Tea"), Cell::Number(30.0)]
+        );
+    }
+
+    #[test]
+    fn rejects_positions_that_are_not_finite() {
+        assert_eq!(
+            resolve_rows("NaN, 1, \"=INDEX(B1:B1, A1)\"")[0][2],
+            Cell::Error(CellError::value("'NaN' is not a number.".to_string()))
+        );
+        for n in [f64::NAN, f64::INFINITY, 0.5] {
+            let args = [
+                Argument::Reference(vec![vec![Value::Number(1.0)]]),
+                Argument::Value(Value::Number(n)),
+            ];
+            assert_eq!(
+                index(&args),
+                Err(CellError::value(format!(
+                    "Row must be at least 1 but is {}.",
+                    n
+                )))
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_empty_ranges() {
+        let empty = [
+            Argument::Value(Value::Number(1.0)),
+            Argument::Reference(vec![]),
+            Argument::Value(Value::Number(1.0)),
+        ];
+        let error = Err(CellError::reference(
+            "The range contains no cells.".to_string(),
+        ));
+        assert_eq!(hlookup(&empty), error);
+        assert_eq!(lookup(&empty[..2]), error);
+        assert_eq!(
+            vlookup(&[
+                Argument::Value(Value::Number(1.0)),
+                Argument::Reference(vec![vec![]]),
+                Argument::Value(Value::Number(1.0)),
+            ]),
+            error
+        );
+    }
+
+    #[test]
+    fn evaluates_match_and_index() {
+        assert_eq!(
+            resolve(&[
+                "=MATCH(\"water\", A1:A4, 0)",
+                "=MATCH(35, C1:C4)",
+                "=MATCH(35, C1:C4, -1)",
+                "=INDEX(A1:C4, 2, 3)",
+                "=INDEX(A1:C4, MATCH(\"Tea\", A1:A4, 0), 2)",
+                "=INDEX(A1:A4, 4)",
+                "=INDEX(A1:C1, 2)",
+                "=MATCH(1, A1:C4)",
+            ]),
+            [
+                Cell::Number(4.0),
+                Cell::Number(3.0),
+                Cell::Number(4.0),
+                Cell::Number(20.0),
+                Cell::Number(2.0),
+                text("Water"),
+                Cell::Number(2.5),
+                Cell::Error(CellError::value(
+                    "Expected a single row or column but got a block of cells.".to_string()
+                ))
+            ]
+        );
+    }
+
+    #[test]
+    fn evaluates_xlookup() {
+        assert_eq!(
+            resolve(&[
+                "=XLOOKUP(\"Cookie\", A1:A4, B1:B4)",
+                "=XLOOKUP(\"Pie\", A1:A4, B1:B4, \"none\")",
+                "=XLOOKUP(\"Pie\", A1:A4, B1:B4)",
+                "=XLOOKUP(25, C1:C4, A1:A4, \"none\", 1)",
+                "=XLOOKUP(25, C1:C4, A1:A4, \"none\", -1)",
+                "=XLOOKUP(1, B1:B4, A1:A4, \"none\", 1, -1)",
+            ]),
+            [
+                Cell::Number(1.2),
+                text("none"),
+                not_found("Pie"),
+                text("Tea"),
+                text("Cookie"),
+                text("Water")
+            ]
+        );
+    }
+}
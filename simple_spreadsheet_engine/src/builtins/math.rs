@@ -0,0 +1,367 @@
+use std::f64::consts::PI;
+
+use crate::{
+    builtins::Builtin,
+    cell_error::CellError,
+    formula::ArithmeticOperator,
+    function_registry::{Argument, Arity},
+    value::Value,
+};
+
+/// Functions on a single number. Results that are not a finite number are errors, e.g. SQRT(-1) or
+/// LN(0), instead of NaN or infinity.
+pub(super) fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::eager("ABS", Arity::exactly(1), |args| unary(args, f64::abs)),
+        Builtin::eager("ACOS", Arity::exactly(1), acos),
+        Builtin::eager("ASIN", Arity::exactly(1), asin),
+        Builtin::eager("ATAN", Arity::exactly(1), |args| unary(args, f64::atan)),
+        Builtin::eager("ATAN2", Arity::exactly(2), atan2),
+        Builtin::eager("CEILING", Arity::between(1, 2), ceiling),
+        Builtin::eager("COS", Arity::exactly(1), |args| unary(args, f64::cos)),
+        Builtin::eager("DEGREES", Arity::exactly(1), |args| {
+            unary(args, f64::to_degrees)
+        }),
+        Builtin::eager("EXP", Arity::exactly(1), |args| unary(args, f64::exp)),
+        Builtin::eager("FLOOR", Arity::between(1, 2), floor),
+        Builtin::eager("INT", Arity::exactly(1), |args| unary(args, f64::floor)),
+        Builtin::eager("LN", Arity::exactly(1), ln),
+        Builtin::eager("LOG", Arity::between(1, 2), log),
+        Builtin::eager("MOD", Arity::exactly(2), mod_),
+        Builtin::eager("PI", Arity::exactly(0), |_| Ok(Value::Number(PI))),
+        Builtin::eager("POWER", Arity::exactly(2), power),
+        Builtin::eager("RADIANS", Arity::exactly(1), |args| {
+            unary(args, f64::to_radians)
+        }),
+        Builtin::eager("ROUND", Arity::between(1, 2), |args| {
+            round(args, f64::round)
+        }),
+        Builtin::eager("ROUNDDOWN", Arity::between(1, 2), |args| {
+            round(args, f64::trunc)
+        }),
+        Builtin::eager("ROUNDUP", Arity::between(1, 2), |args| {
+            round(args, |n| n.abs().ceil().copysign(n))
+        }),
+        Builtin::eager("SIGN", Arity::exactly(1), sign),
+        Builtin::eager("SIN", Arity::exactly(1), |args| unary(args, f64::sin)),
+        Builtin::eager("SQRT", Arity::exactly(1), sqrt),
+        Builtin::eager("TAN", Arity::exactly(1), |args| unary(args, f64::tan)),
+    ]
+}
+
+/// Apply a function to the number.
+fn unary(args: &[Argument], f: fn(f64) -> f64) -> Result<Value, CellError> {
+    Ok(Value::Number(f(args[0].number()?)))
+}
+
+/// The number argument at the index, which must lie in the range.
+fn number_in(
+    args: &[Argument],
+    index: usize,
+    name: &str,
+    range: std::ops::RangeInclusive<f64>,
+    expected: &str,
+) -> Result<f64, CellError> {
+    let n = args[index].number()?;
+    if range.contains(&n) {
+        Ok(n)
+    } else {
+        Err(CellError::num(format!(
+            "{} expects {} but got {}.",
+            name, expected, n
+        )))
+    }
+}
+
+/// Round the number to 15 significant digits, like spreadsheets show them, so an error of the
+/// floating point representation doesn't change how it rounds, e.g. 2.675 * 100 is
+/// 267.49999999999997.
+fn significant(n: f64) -> f64 {
+    format!("{:.14e}", n).parse().unwrap_or(n)
+}
+
+/// ACOS(number)
+fn acos(args: &[Argument]) -> Result<Value, CellError> {
+    let n = number_in(args, 0, "ACOS", -1.0..=1.0, "a number from -1 to 1")?;
+    Ok(Value::Number(n.acos()))
+}
+
+/// ASIN(number)
+fn asin(args: &[Argument]) -> Result<Value, CellError> {
+    let n = number_in(args, 0, "ASIN", -1.0..=1.0, "a number from -1 to 1")?;
+    Ok(Value::Number(n.asin()))
+}
+
+/// ATAN2(x, y), the angle of the point (x, y) from the x-axis.
+fn atan2(args: &[Argument]) -> Result<Value, CellError> {
+    let x = args[0].number()?;
+    let y = args[1].number()?;
+    if x == 0.0 && y == 0.0 {
+        return Err(CellError::division_by_zero(
+            "ATAN2 has no angle for the origin.".to_string(),
+        ));
+    }
+    Ok(Value::Number(y.atan2(x)))
+}
+
+/// Round the number to a multiple of the significance with `f`. A positive number can't be rounded
+/// to a negative multiple.
+fn multiple(args: &[Argument], name: &str, f: fn(f64) -> f64) -> Result<Value, CellError> {
+    let n = args[0].number()?;
+    let significance = match args.get(1) {
+        Some(arg) => arg.number()?,
+        None => 1.0,
+    };
+    if n > 0.0 && significance < 0.0 {
+        return Err(CellError::num(format!(
+            "{} can't round the positive number {} to a multiple of {}.",
+            name, n, significance
+        )));
+    }
+    if significance == 0.0 {
+        return Ok(Value::Number(0.0));
+    }
+    Ok(Value::Number(
+        f(significant(n / significance)) * significance,
+    ))
+}
+
+/// CEILING(number, [significance]) rounds up to a multiple of the significance, 1 if left out.
+fn ceiling(args: &[Argument]) -> Result<Value, CellError> {
+    multiple(args, "CEILING", f64::ceil)
+}
+
+/// FLOOR(number, [significance]) rounds down to a multiple of the significance, 1 if left out.
+fn floor(args: &[Argument]) -> Result<Value, CellError> {
+    multiple(args, "FLOOR", f64::floor)
+}
+
+/// LN(number)
+fn ln(args: &[Argument]) -> Result<Value, CellError> {
+    let n = number_in(
+        args,
+        0,
+        "LN",
+        f64::MIN_POSITIVE..=f64::MAX,
+        "a positive number",
+    )?;
+    Ok(Value::Number(n.ln()))
+}
+
+/// LOG(number, [base]), with base 10 if left out.
+fn log(args: &[Argument]) -> Result<Value, CellError> {
+    let n = number_in(
+        args,
+        0,
+        "LOG",
+        f64::MIN_POSITIVE..=f64::MAX,
+        "a positive number",
+    )?;
+    let base = match args.get(1) {
+        Some(_) => number_in(
+            args,
+            1,
+            "LOG",
+            f64::MIN_POSITIVE..=f64::MAX,
+            "a positive base",
+        )?,
+        None => 10.0,
+    };
+    if base == 1.0 {
+        return Err(CellError::division_by_zero(
+            "LOG can't use the base 1.".to_string(),
+        ));
+    }
+    // log10 and log2 are exact for powers of their base, where ln(n) / ln(base) may not be.
+    Ok(Value::Number(match base {
+        10.0 => n.log10(),
+        2.0 => n.log2(),
+        _ => n.log(base),
+    }))
+}
+
+/// MOD(number, divisor), the remainder of the division, which has the sign of the divisor.
+fn mod_(args: &[Argument]) -> Result<Value, CellError> {
+    let n = args[0].number()?;
+    let divisor = args[1].number()?;
+    if divisor == 0.0 {
+        return Err(CellError::division_by_zero(format!(
+            "Cannot divide {} by zero.",
+            n
+        )));
+    }
+    Ok(Value::Number(n - divisor * (n / divisor).floor()))
+}
+
+/// POWER(number, power), the same as number ** power.
+fn power(args: &[Argument]) -> Result<Value, CellError> {
+    let n = ArithmeticOperator::Exponentiation.apply(args[0].number()?, args[1].number()?)?;
+    Ok(Value::Number(n))
+}
+
+/// Round the number to a number of digits after the decimal point with `f`, or before it if the
+/// number of digits is negative. The digits are 0 if left out. Digits beyond the precision of a
+/// number leave it as it is.
+fn round(args: &[Argument], f: fn(f64) -> f64) -> Result<Value, CellError> {
+    let n = args[0].number()?;
+    let digits = match args.get(1) {
+        Some(arg) => arg.number()?.trunc().clamp(-308.0, 308.0),
+        None => 0.0,
+    };
+    // Divide by a power of 10 rather than multiply by its inverse, which isn't exact.
+    let scale = 10f64.powf(digits.abs());
+    if digits < 0.0 {
+        return Ok(Value::Number(f(significant(n / scale)) * scale));
+    }
+    if !(n * scale).is_finite() {
+        return Ok(Value::Number(n));
+    }
+    Ok(Value::Number(f(significant(n * scale)) / scale))
+}
+
+/// SIGN(number), 1 for positive numbers, -1 for negative numbers and 0 for 0.
+fn sign(args: &[Argument]) -> Result<Value, CellError> {
+    let n = args[0].number()?;
+    Ok(Value::Number(if n == 0.0 { 0.0 } else { n.signum() }))
+}
+
+/// SQRT(number)
+fn sqrt(args: &[Argument]) -> Result<Value, CellError> {
+    let n = number_in(args, 0, "SQRT", 0.0..=f64::MAX, "a number of at least 0")?;
+    Ok(Value::Number(n.sqrt()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::resolve;
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
+
+    fn numbers(numbers: &[f64]) -> Vec<Cell> {
+        numbers.iter().map(|n| Cell::Number(*n)).collect()
+    }
+
+    #[test]
+    fn rounds_numbers() {
+        assert_eq!(
+            resolve(&[
+                "=ROUND(2.675, 2)",
+                "=ROUND(-2.5)",
+                "=ROUND(1234.5, -2)",
+                "=ROUNDUP(0.1 + 0.2, 1)",
+                "=ROUNDUP(-1.21, 1)",
+                "=ROUNDDOWN(-1.29, 1)",
+                "=INT(-1.5)",
+                "=CEILING(2.1, 0.5)",
+                "=CEILING(-2.5, 2)",
+                "=FLOOR(7, 3)",
+                "=FLOOR(-2.5, -2)",
+            ]),
+            numbers(&[2.68, -3.0, 1200.0, 0.3, -1.3, -1.2, -2.0, 2.5, -2.0, 6.0, -2.0])
+        );
+        assert_eq!(
+            resolve(&[
+                "=ROUND(1, 400)",
+                "=ROUND(1.25, 308)",
+                "=ROUNDDOWN(10 ** 300, 20)",
+                "=ROUNDUP(-2.5, 10 ** 10)",
+                "=ROUND(123, -400)",
+            ]),
+            numbers(&[1.0, 1.25, 1e300, -2.5, 0.0])
+        );
+        assert_eq!(
+            resolve(&[
+                "=ROUND(123456789, -5)",
+                "=ROUND(-123456789, -5)",
+                "=ROUNDUP(123456789, -5)",
+                "=ROUNDUP(-123400001, -5)",
+                "=ROUNDDOWN(123456789, -5)",
+                "=ROUNDDOWN(-123499999, -5)",
+                "=ROUND(4567.8, -3)",
+                "=ROUNDUP(1, -308)",
+            ]),
+            numbers(&[
+                123500000.0,
+                -123500000.0,
+                123500000.0,
+                -123500000.0,
+                123400000.0,
+                -123400000.0,
+                5000.0,
+                1e308
+            ])
+        );
+    }
+
+    #[test]
+    fn computes_math_functions() {
+        assert_eq!(
+            resolve(&[
+                "=ABS(-3)",
+                "=MOD(-7, 3)",
+                "=MOD(7, -3)",
+                "=POWER(2, 10)",
+                "=SQRT(16)",
+                "=LOG(1000)",
+                "=LOG(8, 2)",
+                "=LN(EXP(2))",
+                "=SIGN(-0.5)",
+                "=DEGREES(PI())",
+                "=ATAN2(1, 1) * 4",
+            ]),
+            numbers(&[
+                3.0,
+                2.0,
+                -2.0,
+                1024.0,
+                4.0,
+                3.0,
+                3.0,
+                2.0,
+                -1.0,
+                180.0,
+                std::f64::consts::PI
+            ])
+        );
+    }
+
+    #[test]
+    fn reports_domain_errors() {
+        assert_eq!(
+            resolve(&[
+                "=SQRT(-4)",
+                "=LN(0)",
+                "=LOG(10, 1)",
+                "=MOD(1, 0)",
+                "=POWER(-8, 1 / 3)",
+                "=ASIN(2)",
+                "=EXP(1000)",
+                "=1 / (2 - 2)",
+            ]),
+            [
+                Cell::Error(CellError::num(
+                    "SQRT expects a number of at least 0 but got -4.".to_string()
+                )),
+                Cell::Error(CellError::num(
+                    "LN expects a positive number but got 0.".to_string()
+                )),
+                Cell::Error(CellError::division_by_zero(
+                    "LOG can't use the base 1.".to_string()
+                )),
+                Cell::Error(CellError::division_by_zero(
+                    "Cannot divide 1 by zero.".to_string()
+                )),
+                Cell::Error(CellError::num(
+                    "-8 ** 0.3333333333333333 has no finite result.".to_string()
+                )),
+                Cell::Error(CellError::num(
+                    "ASIN expects a number from -1 to 1 but got 2.".to_string()
+                )),
+                Cell::Error(CellError::num("EXP has no finite result.".to_string())),
+                Cell::Error(CellError::division_by_zero(
+                    "Cannot divide 1 by zero.".to_string()
+                )),
+            ]
+        );
+    }
+}
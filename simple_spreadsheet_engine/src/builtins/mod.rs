@@ -0,0 +1,123 @@
+mod aggregate;
+mod conditional;
+mod criteria;
+mod dates;
+mod financial;
+mod info;
+mod lookup;
+mod math;
+mod statistics;
+mod text;
+
+use crate::{
+    cell_error::CellError,
+    context::Context,
+    function_registry::{Argument, Arguments, Arity, FunctionRegistry, SpreadsheetFunction},
+    value::Value,
+};
+
+/// A one-based position argument, e.g. the column index of VLOOKUP. Positions that are below 1 or
+/// not finite are an error.
+fn position(arg: &Argument, name: &str) -> Result<usize, CellError> {
+    let n = arg.number()?;
+    if !(n >= 1.0 && n.is_finite()) {
+        return Err(CellError::value(format!(
+            "{} must be at least 1 but is {}.",
+            name, n
+        )));
+    }
+    Ok(n as usize)
+}
+
+type Eager = fn(&[Argument]) -> Result<Value, CellError>;
+type Lazy = fn(&Arguments) -> Result<Value, CellError>;
+
+/// How a built-in function computes its result.
+enum Evaluate {
+    /// From the values of all arguments.
+    Eager(Eager),
+    /// From the unevaluated arguments, so only the arguments that are needed get evaluated.
+    Lazy(Lazy),
+}
+
+/// A built-in function: a name and arity with a plain function that computes the result.
+pub(crate) struct Builtin {
+    name: &'static str,
+    arity: Arity,
+    evaluate: Evaluate,
+}
+
+impl Builtin {
+    fn eager(name: &'static str, arity: Arity, evaluate: Eager) -> Self {
+        Builtin {
+            name,
+            arity,
+            evaluate: Evaluate::Eager(evaluate),
+        }
+    }
+
+    fn lazy(name: &'static str, arity: Arity, evaluate: Lazy) -> Self {
+        Builtin {
+            name,
+            arity,
+            evaluate: Evaluate::Lazy(evaluate),
+        }
+    }
+}
+
+impl SpreadsheetFunction for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn arity(&self) -> Arity {
+        self.arity
+    }
+
+    fn evaluate(&self, args: &[Argument], context: &Context) -> Result<Value, CellError> {
+        match self.evaluate {
+            Evaluate::Eager(evaluate) => evaluate(args),
+            Evaluate::Lazy(evaluate) => evaluate(&Arguments::evaluated(args, context)),
+        }
+    }
+
+    fn call(&self, args: &Arguments) -> Result<Value, CellError> {
+        match self.evaluate {
+            Evaluate::Eager(evaluate) => evaluate(&args.evaluate_all()?),
+            Evaluate::Lazy(evaluate) => evaluate(args),
+        }
+    }
+}
+
+/// Add the built-in functions to the registry.
+pub fn register(registry: &mut FunctionRegistry) {
+    for builtin in aggregate::builtins()
+        .into_iter()
+        .chain(conditional::builtins())
+        .chain(criteria::builtins())
+        .chain(dates::builtins())
+        .chain(financial::builtins())
+        .chain(info::builtins())
+        .chain(lookup::builtins())
+        .chain(math::builtins())
+        .chain(statistics::builtins())
+        .chain(text::builtins())
+    {
+        registry.register(builtin);
+    }
+}
+
+/// Resolve the rows of cells in the CSV input on 2015-05-20, for the tests of the built-ins.
+#[cfg(test)]
+fn resolve_rows(input: &str) -> Vec<Vec<crate::cell::Cell>> {
+    let mut sheet =
+        crate::sheet::Sheet::parse_input(input.to_string(), &FunctionRegistry::default()).unwrap();
+    sheet.context = Context::with_today(2015, 5, 20);
+    sheet.resolve().cells
+}
+
+/// Resolve a single row of cells, see `resolve_rows`. Cells that contain a comma are quoted.
+#[cfg(test)]
+fn resolve(cells: &[&str]) -> Vec<crate::cell::Cell> {
+    resolve_rows(&crate::csv::join(cells)).remove(0)
+}
@@ -0,0 +1,469 @@
+use std::cmp::Ordering;
+
+use crate::{
+    builtins::Builtin,
+    cell_error::CellError,
+    function_registry::{numbers, Argument, Arity},
+    value::Value,
+};
+
+/// Statistical functions. Like the aggregate functions they only use the numbers of cell references
+/// and ranges, and they fail when there are too few numbers to compute a result.
+pub(super) fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::eager("CORREL", Arity::exactly(2), correl),
+        Builtin::eager("COVARIANCE.P", Arity::exactly(2), covariance_p),
+        Builtin::eager("COVARIANCE.S", Arity::exactly(2), covariance_s),
+        Builtin::eager("LARGE", Arity::exactly(2), large),
+        Builtin::eager("MEDIAN", Arity::at_least(1), median),
+        Builtin::eager("MODE", Arity::at_least(1), mode),
+        Builtin::eager("PERCENTILE", Arity::exactly(2), percentile),
+        Builtin::eager("QUARTILE", Arity::exactly(2), quartile),
+        Builtin::eager("RANK", Arity::between(2, 3), rank),
+        Builtin::eager("SMALL", Arity::exactly(2), small),
+        Builtin::eager("STDEV", Arity::at_least(1), stdev_s),
+        Builtin::eager("STDEV.P", Arity::at_least(1), stdev_p),
+        Builtin::eager("STDEV.S", Arity::at_least(1), stdev_s),
+        Builtin::eager("SUMPRODUCT", Arity::at_least(1), sumproduct),
+        Builtin::eager("VAR.P", Arity::at_least(1), var_p),
+        Builtin::eager("VAR.S", Arity::at_least(1), var_s),
+    ]
+}
+
+/// Check that there are enough numbers for the function `name`.
+fn expect_numbers(name: &str, nums: &[f64], min: usize) -> Result<(), CellError> {
+    too_few_numbers(name, nums, min).map_or(Ok(()), |e| Err(CellError::value(e)))
+}
+
+/// The message for a function `name` that gets fewer than `min` numbers, None if there are enough.
+fn too_few_numbers(name: &str, nums: &[f64], min: usize) -> Option<String> {
+    if nums.len() >= min {
+        return None;
+    }
+    Some(format!(
+        "{} expects at least {} {} but got {}.",
+        name,
+        min,
+        if min == 1 { "number" } else { "numbers" },
+        nums.len()
+    ))
+}
+
+/// The numbers sorted from small to large.
+fn sorted(nums: Vec<f64>) -> Vec<f64> {
+    let mut nums = nums;
+    nums.sort_by(|a, b| a.total_cmp(b));
+    nums
+}
+
+/// Mean and sum of squared differences from the mean, with Welford's algorithm, which doesn't lose
+/// precision when the numbers are large compared to their differences.
+fn moments(nums: &[f64]) -> (f64, f64) {
+    let mut mean = 0.0;
+    let mut squares = 0.0;
+    for (i, x) in nums.iter().enumerate() {
+        let diff = x - mean;
+        mean += diff / (i + 1) as f64;
+        squares += diff * (x - mean);
+    }
+    (mean, squares)
+}
+
+/// Variance of the numbers, of a sample if `sample` is set, else of the whole population. Too few
+/// numbers is a division by zero, like in spreadsheets.
+fn variance(name: &str, args: &[Argument], sample: bool) -> Result<f64, CellError> {
+    let nums = numbers(args)?;
+    let min = if sample { 2 } else { 1 };
+    if let Some(e) = too_few_numbers(name, &nums, min) {
+        return Err(CellError::division_by_zero(e));
+    }
+    let (_, squares) = moments(&nums);
+    Ok(squares / (nums.len() + 1 - min) as f64)
+}
+
+/// Pairs of numbers at the same position of two ranges. Pairs where either value is not a number
+/// are left out.
+fn pairs(name: &str, args: &[Argument]) -> Result<Vec<(f64, f64)>, CellError> {
+    let (xs, ys) = (args[0].values(), args[1].values());
+    if xs.len() != ys.len() {
+        return Err(CellError::value(format!(
+            "{} expects ranges of the same size but got {} and {} values.",
+            name,
+            xs.len(),
+            ys.len()
+        )));
+    }
+    Ok(xs
+        .iter()
+        .zip(ys)
+        .filter_map(|(x, y)| Some((x.as_number()?, y.as_number()?)))
+        .collect())
+}
+
+/// Means and co-moment of pairs of numbers, and the sums of squared differences from the mean of
+/// each, in a single stable pass like `moments`.
+fn co_moments(pairs: &[(f64, f64)]) -> (f64, f64, f64) {
+    let (mut mean_x, mut mean_y) = (0.0, 0.0);
+    let (mut squares_x, mut squares_y, mut co_moment) = (0.0, 0.0, 0.0);
+    for (i, (x, y)) in pairs.iter().enumerate() {
+        let n = (i + 1) as f64;
+        let (diff_x, diff_y) = (x - mean_x, y - mean_y);
+        mean_x += diff_x / n;
+        mean_y += diff_y / n;
+        squares_x += diff_x * (x - mean_x);
+        squares_y += diff_y * (y - mean_y);
+        co_moment += diff_x * (y - mean_y);
+    }
+    (squares_x, squares_y, co_moment)
+}
+
+fn covariance(name: &str, args: &[Argument], sample: bool) -> Result<Value, CellError> {
+    let pairs = pairs(name, args)?;
+    let min = if sample { 2 } else { 1 };
+    if pairs.len() < min {
+        return Err(CellError::value(format!(
+            "{} expects at least {} {} of numbers but got {}.",
+            name,
+            min,
+            if min == 1 { "pair" } else { "pairs" },
+            pairs.len()
+        )));
+    }
+    let (_, _, co_moment) = co_moments(&pairs);
+    Ok(Value::Number(co_moment / (pairs.len() + 1 - min) as f64))
+}
+
+/// Interpolate the value at the fraction `k` from 0 to 1 of the sorted numbers, where 0 is the
+/// smallest and 1 the largest number.
+fn interpolate(sorted: &[f64], k: f64) -> f64 {
+    let position = k * (sorted.len() - 1) as f64;
+    let below = position.floor() as usize;
+    match sorted.get(below + 1) {
+        Some(above) => sorted[below] + (position - below as f64) * (above - sorted[below]),
+        None => sorted[below],
+    }
+}
+
+/// The k-th number, where 1 is the first, of the numbers of the first argument in the given order.
+/// A position beyond the numbers is #NUM!, like in spreadsheets.
+fn nth(name: &str, args: &[Argument], order: fn(f64, f64) -> Ordering) -> Result<Value, CellError> {
+    let mut nums = numbers(&args[..1])?;
+    if let Some(e) = too_few_numbers(name, &nums, 1) {
+        return Err(CellError::num(e));
+    }
+    nums.sort_by(|a, b| order(*a, *b));
+    let k = args[1].number()?;
+    if !(k >= 1.0 && k.is_finite()) || k as usize > nums.len() {
+        return Err(CellError::num(format!(
+            "{} expects a position from 1 to {} but got {}.",
+            name,
+            nums.len(),
+            k
+        )));
+    }
+    Ok(Value::Number(nums[k as usize - 1]))
+}
+
+/// CORREL(range1, range2), the Pearson correlation coefficient.
+fn correl(args: &[Argument]) -> Result<Value, CellError> {
+    let pairs = pairs("CORREL", args)?;
+    let (squares_x, squares_y, co_moment) = co_moments(&pairs);
+    if squares_x == 0.0 || squares_y == 0.0 {
+        return Err(CellError::value(
+            "CORREL expects ranges with numbers that are not all the same.".to_string(),
+        ));
+    }
+    Ok(Value::Number(co_moment / (squares_x * squares_y).sqrt()))
+}
+
+/// COVARIANCE.P(range1, range2)
+fn covariance_p(args: &[Argument]) -> Result<Value, CellError> {
+    covariance("COVARIANCE.P", args, false)
+}
+
+/// COVARIANCE.S(range1, range2)
+fn covariance_s(args: &[Argument]) -> Result<Value, CellError> {
+    covariance("COVARIANCE.S", args, true)
+}
+
+/// LARGE(range, k), the k-th largest number.
+fn large(args: &[Argument]) -> Result<Value, CellError> {
+    nth("LARGE", args, |a, b| b.total_cmp(&a))
+}
+
+/// MEDIAN(value1, [value2], ...), the mean of the two middle numbers if there is an even count.
+fn median(args: &[Argument]) -> Result<Value, CellError> {
+    let nums = sorted(numbers(args)?);
+    expect_numbers("MEDIAN", &nums, 1)?;
+    Ok(Value::Number(interpolate(&nums, 0.5)))
+}
+
+/// MODE(value1, [value2], ...), the number that occurs most often. If several numbers occur equally
+/// often, the one that comes first.
+fn mode(args: &[Argument]) -> Result<Value, CellError> {
+    let nums = numbers(args)?;
+    expect_numbers("MODE", &nums, 1)?;
+    let mut counts: Vec<(f64, usize)> = Vec::new();
+    for n in nums {
+        match counts.iter_mut().find(|(m, _)| *m == n) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((n, 1)),
+        }
+    }
+    // max_by_key returns the last maximum, so look from the back to find the first one.
+    match counts.iter().rev().max_by_key(|(_, count)| *count) {
+        Some((n, count)) if *count > 1 => Ok(Value::Number(*n)),
+        _ => Err(CellError::not_available(
+            "No number occurs more than once.".to_string(),
+        )),
+    }
+}
+
+/// PERCENTILE(range, k), the k-th percentile for k from 0 to 1, interpolating between the numbers.
+fn percentile(args: &[Argument]) -> Result<Value, CellError> {
+    let nums = sorted(numbers(&args[..1])?);
+    expect_numbers("PERCENTILE", &nums, 1)?;
+    let k = args[1].number()?;
+    if !(0.0..=1.0).contains(&k) {
+        return Err(CellError::value(format!(
+            "PERCENTILE expects a percentile from 0 to 1 but got {}.",
+            k
+        )));
+    }
+    Ok(Value::Number(interpolate(&nums, k)))
+}
+
+/// QUARTILE(range, quart) for quart 0 (minimum), 1, 2 (median), 3 or 4 (maximum).
+fn quartile(args: &[Argument]) -> Result<Value, CellError> {
+    let nums = sorted(numbers(&args[..1])?);
+    expect_numbers("QUARTILE", &nums, 1)?;
+    let quart = args[1].number()?.trunc();
+    if !(0.0..=4.0).contains(&quart) {
+        return Err(CellError::value(format!(
+            "QUARTILE expects a quartile from 0 to 4 but got {}.",
+            quart
+        )));
+    }
+    Ok(Value::Number(interpolate(&nums, quart / 4.0)))
+}
+
+/// RANK(number, range, [order]), the position of the number in the range, from the largest number
+/// if the order is 0 or left out, else from the smallest. Equal numbers get the same rank.
+fn rank(args: &[Argument]) -> Result<Value, CellError> {
+    let number = args[0].number()?;
+    let nums = numbers(&args[1..2])?;
+    if !nums.contains(&number) {
+        return Err(CellError::not_available(format!(
+            "Could not find '{}'.",
+            number
+        )));
+    }
+    let ascending = match args.get(2) {
+        Some(arg) => arg.number()? != 0.0,
+        None => false,
+    };
+    let before = nums
+        .iter()
+        .filter(|n| {
+            if ascending {
+                **n < number
+            } else {
+                **n > number
+            }
+        })
+        .count();
+    Ok(Value::Number((before + 1) as f64))
+}
+
+/// SMALL(range, k), the k-th smallest number.
+fn small(args: &[Argument]) -> Result<Value, CellError> {
+    nth("SMALL", args, |a, b| a.total_cmp(&b))
+}
+
+/// STDEV.P(value1, [value2], ...), the standard deviation of a whole population.
+fn stdev_p(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(variance("STDEV.P", args, false)?.sqrt()))
+}
+
+/// STDEV.S(value1, [value2], ...), the standard deviation of a sample.
+fn stdev_s(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(variance("STDEV.S", args, true)?.sqrt()))
+}
+
+/// SUMPRODUCT(range1, [range2], ...) multiplies the values at the same position of each range and
+/// sums the products. Values that are not numbers count as 0.
+fn sumproduct(args: &[Argument]) -> Result<Value, CellError> {
+    let ranges: Vec<Vec<&Value>> = args.iter().map(|arg| arg.values()).collect();
+    let len = ranges[0].len();
+    if let Some(range) = ranges.iter().find(|range| range.len() != len) {
+        return Err(CellError::value(format!(
+            "SUMPRODUCT expects ranges of the same size but got {} and {} values.",
+            len,
+            range.len()
+        )));
+    }
+    let sum = (0..len)
+        .map(|i| {
+            ranges
+                .iter()
+                .map(|range| range[i].as_number().unwrap_or(0.0))
+                .product::<f64>()
+        })
+        .fold(0.0, |acc, n| acc + n);
+    Ok(Value::Number(sum))
+}
+
+/// VAR.P(value1, [value2], ...), the variance of a whole population.
+fn var_p(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(variance("VAR.P", args, false)?))
+}
+
+/// VAR.S(value1, [value2], ...), the variance of a sample.
+fn var_s(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(variance("VAR.S", args, true)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::resolve_rows;
+    use crate::builtins::statistics::{large, moments, small};
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
+    use crate::function_registry::Argument;
+    use crate::value::Value;
+
+    /// Resolve the cells of the last column, with numbers in the first columns.
+    fn resolve(input: &str) -> Vec<Cell> {
+        resolve_rows(input)
+            .into_iter()
+            .filter_map(|mut row| row.pop())
+            .collect()
+    }
+
+    fn numbers(numbers: &[f64]) -> Vec<Cell> {
+        numbers.iter().map(|n| Cell::Number(*n)).collect()
+    }
+
+    fn error(message: &str) -> Cell {
+        Cell::Error(CellError::value(message.to_string()))
+    }
+
+    #[test]
+    fn rejects_positions_that_are_not_finite() {
+        for k in [f64::NAN, f64::INFINITY] {
+            let args = [
+                Argument::Reference(vec![vec![Value::Number(1.0), Value::Number(2.0)]]),
+                Argument::Value(Value::Number(k)),
+            ];
+            let error = |name: &str| {
+                Err(CellError::num(format!(
+                    "{} expects a position from 1 to 2 but got {}.",
+                    name, k
+                )))
+            };
+            assert_eq!(large(&args), error("LARGE"));
+            assert_eq!(small(&args), error("SMALL"));
+        }
+    }
+
+    #[test]
+    fn keeps_precision_of_large_numbers() {
+        let nums = [1e9 + 4.0, 1e9 + 7.0, 1e9 + 13.0, 1e9 + 16.0];
+        assert_eq!(moments(&nums), (1e9 + 10.0, 90.0));
+    }
+
+    #[test]
+    fn computes_spread() {
+        assert_eq!(
+            resolve(
+                "1,=MEDIAN(A1:A4)
+                 2,=MEDIAN(A1:A3)
+                 4,=VAR.P(A1:A4)
+                 7,=VAR.S(A1:A4)
+                 ,=STDEV.P(A1:A4)
+                 ,\"=STDEV.S(2, 4, 4, 4, 5, 5, 7, 9)\"
+                 ,\"=STDEV(2, 4, 4, 4, 5, 5, 7, 9)\""
+            ),
+            numbers(&[
+                3.0,
+                2.0,
+                5.25,
+                7.0,
+                5.25f64.sqrt(),
+                2.138089935299395,
+                2.138089935299395
+            ])
+        );
+    }
+
+    #[test]
+    fn fails_without_enough_numbers() {
+        assert_eq!(
+            resolve(
+                "a,=MEDIAN(A1:A2)
+                 ,=VAR.S(5)
+                 ,=STDEV(A1)
+                 ,\"=LARGE(A1, 1)\"
+                 ,\"=MODE(1, 2, 3)\""
+            ),
+            [
+                error("MEDIAN expects at least 1 number but got 0."),
+                Cell::Error(CellError::division_by_zero(
+                    "VAR.S expects at least 2 numbers but got 1."
+                )),
+                Cell::Error(CellError::division_by_zero(
+                    "STDEV.S expects at least 2 numbers but got 0."
+                )),
+                Cell::Error(CellError::num("LARGE expects at least 1 number but got 0.")),
+                Cell::Error(CellError::not_available(
+                    "No number occurs more than once.".to_string()
+                ))
+            ]
+        );
+    }
+
+    #[test]
+    fn orders_numbers() {
+        assert_eq!(
+            resolve(
+                "3,=MODE(A1:A5)
+                 1,\"=PERCENTILE(A1:A5, 0.625)\"
+                 4,\"=QUARTILE(A1:A5, 3)\"
+                 1,\"=RANK(3, A1:A5)\"
+                 5,\"=RANK(1, A1:A5, 1)\"
+                  ,\"=LARGE(A1:A5, 2)\"
+                  ,\"=SMALL(A1:A5, 3)\""
+            ),
+            numbers(&[1.0, 3.5, 4.0, 3.0, 1.0, 4.0, 3.0])
+        );
+        assert_eq!(
+            resolve("1,\"=SMALL(A1:A2, 2)\"\n,\"=PERCENTILE(A1, 2)\""),
+            [
+                Cell::Error(CellError::num(
+                    "SMALL expects a position from 1 to 1 but got 2."
+                )),
+                error("PERCENTILE expects a percentile from 0 to 1 but got 2.")
+            ]
+        );
+    }
+
+    #[test]
+    fn relates_ranges() {
+        assert_eq!(
+            resolve(
+                "1,2,\"=CORREL(A1:A4, B1:B4)\"
+                 2,4,\"=COVARIANCE.P(A1:A4, B1:B4)\"
+                 3,6,\"=COVARIANCE.S(A1:A3, B1:B3)\"
+                 x,1,\"=SUMPRODUCT(A1:A4, B1:B4)\""
+            ),
+            numbers(&[1.0, 4.0 / 3.0, 2.0, 28.0])
+        );
+        assert_eq!(
+            resolve("1,1,\"=CORREL(A1:A2, B1:B2)\"\n1,,\"=SUMPRODUCT(A1:A2, A1:A1)\""),
+            [
+                error("CORREL expects ranges with numbers that are not all the same."),
+                error("SUMPRODUCT expects ranges of the same size but got 2 and 1 values.")
+            ]
+        );
+    }
+}
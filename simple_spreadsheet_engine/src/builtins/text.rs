@@ -0,0 +1,578 @@
+use crate::{
+    builtins::{dates, position, Builtin},
+    cell::{parse_number, parse_percent},
+    cell_error::CellError,
+    date,
+    function_registry::{Argument, Arity},
+    value::Value,
+};
+
+/// Functions that read or produce text. Other values are used as the text they are shown as, e.g.
+/// LEN(1234) is 4.
+pub(super) fn builtins() -> Vec<Builtin> {
+    vec![
+        Builtin::eager("LEFT", Arity::between(1, 2), left),
+        Builtin::eager("LEN", Arity::exactly(1), len),
+        Builtin::eager("LOWER", Arity::exactly(1), lower),
+        Builtin::eager("MID", Arity::exactly(3), mid),
+        Builtin::eager("RIGHT", Arity::between(1, 2), right),
+        Builtin::eager("SPLIT", Arity::exactly(3), split),
+        Builtin::eager("SUBSTITUTE", Arity::between(3, 4), substitute),
+        Builtin::eager("TEXT", Arity::exactly(2), text_),
+        Builtin::eager("TRIM", Arity::exactly(1), trim),
+        Builtin::eager("UPPER", Arity::exactly(1), upper),
+        Builtin::eager("VALUE", Arity::exactly(1), value),
+    ]
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// The argument as text.
+fn text(arg: &Argument) -> Result<String, CellError> {
+    Ok(arg.value()?.to_string())
+}
+
+/// The argument as a number of characters, which must not be negative.
+fn count(arg: &Argument, name: &str) -> Result<usize, CellError> {
+    match arg.number()? {
+        n if n < 0.0 || n.is_nan() => Err(CellError::value(format!(
+            "{} expects a count of characters of at least 0 but got {}.",
+            name, n
+        ))),
+        n => Ok(n as usize),
+    }
+}
+
+/// LEFT(text, [count]) takes the first characters, one if the count is left out.
+fn left(args: &[Argument]) -> Result<Value, CellError> {
+    let count = match args.get(1) {
+        Some(arg) => count(arg, "LEFT")?,
+        None => 1,
+    };
+    Ok(Value::Text(text(&args[0])?.chars().take(count).collect()))
+}
+
+/// LEN(text)
+fn len(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Number(text(&args[0])?.chars().count() as f64))
+}
+
+/// LOWER(text)
+fn lower(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Text(text(&args[0])?.to_lowercase()))
+}
+
+/// MID(text, start, count) takes the characters from the start, where 1 is the first character.
+fn mid(args: &[Argument]) -> Result<Value, CellError> {
+    let start = position(&args[1], "The start of MID")?;
+    let count = count(&args[2], "MID")?;
+    Ok(Value::Text(
+        text(&args[0])?
+            .chars()
+            .skip(start - 1)
+            .take(count)
+            .collect(),
+    ))
+}
+
+/// RIGHT(text, [count]) takes the last characters, one if the count is left out.
+fn right(args: &[Argument]) -> Result<Value, CellError> {
+    let count = match args.get(1) {
+        Some(arg) => count(arg, "RIGHT")?,
+        None => 1,
+    };
+    let chars: Vec<char> = text(&args[0])?.chars().collect();
+    Ok(Value::Text(
+        chars[chars.len().saturating_sub(count)..].iter().collect(),
+    ))
+}
+
+/// SPLIT(text, delimiter, index) splits the text at each delimiter and takes the part at the index,
+/// where 1 is the first part. A cell only holds a single value, so unlike in other spreadsheets
+/// the parts don't spill into the next cells.
+fn split(args: &[Argument]) -> Result<Value, CellError> {
+    let text = text(&args[0])?;
+    let delimiter = self::text(&args[1])?;
+    if delimiter.is_empty() {
+        return Err(CellError::value(
+            "SPLIT expects a delimiter that is not empty.".to_string(),
+        ));
+    }
+    let index = args[2].number()?;
+    let parts: Vec<&str> = text.split(delimiter.as_str()).collect();
+    match index {
+        index if index >= 1.0 && index as usize <= parts.len() => {
+            Ok(Value::Text(parts[index as usize - 1].to_string()))
+        }
+        _ => Err(CellError::value(format!(
+            "'{}' has no part {} when split at '{}'.",
+            text, index, delimiter
+        ))),
+    }
+}
+
+/// SUBSTITUTE(text, old_text, new_text, [instance]) replaces all occurrences of the old text, or
+/// only the given one, where 1 is the first occurrence.
+fn substitute(args: &[Argument]) -> Result<Value, CellError> {
+    let text = text(&args[0])?;
+    let old = self::text(&args[1])?;
+    let new = self::text(&args[2])?;
+    if old.is_empty() {
+        return Ok(Value::Text(text));
+    }
+    let out = match args.get(3) {
+        None => text.replace(&old, &new),
+        Some(arg) => {
+            let instance = position(arg, "The instance of SUBSTITUTE")?;
+            match text.match_indices(&old).nth(instance - 1) {
+                Some((i, _)) => format!("{}{}{}", &text[..i], new, &text[i + old.len()..]),
+                None => text,
+            }
+        }
+    };
+    Ok(Value::Text(out))
+}
+
+/// TEXT(value, format) formats a number or date. Formats with day, year, hour or second codes like
+/// "dd/mm/yyyy" or "hh:mm" and without digit placeholders are date formats, others like "#,##0.00",
+/// "0.0%" or "0 items" are number formats. A format can have up to three sections separated by
+/// semicolons, for positive numbers, negative numbers shown without their sign and zero.
+fn text_(args: &[Argument]) -> Result<Value, CellError> {
+    let format = text(&args[1])?;
+    let sections: Vec<&str> = format.split(';').collect();
+    let (value, section) = match sections[..] {
+        [section] => (args[0].value()?.clone(), section),
+        [positive, negative, ..] if sections.len() <= 3 => {
+            let number = args[0].number()?;
+            match sections[..] {
+                _ if number < 0.0 => (Value::Number(-number), negative),
+                [_, _, zero] if number == 0.0 => (Value::Number(number), zero),
+                _ => (Value::Number(number), positive),
+            }
+        }
+        _ => {
+            return Err(CellError::value(format!(
+                "The format '{}' has more than three sections.",
+                format
+            )))
+        }
+    };
+
+    let is_date_format = section
+        .chars()
+        .any(|c| matches!(c.to_ascii_lowercase(), 'd' | 'y' | 'h' | 's'))
+        && !section.contains(['0', '#']);
+    if is_date_format {
+        let serial = dates::serial(&value)?;
+        Ok(Value::Text(format_date(serial, section)))
+    } else {
+        let number = Argument::Value(value).number()?;
+        Ok(Value::Text(format_number(number, section)))
+    }
+}
+
+/// Format a number with a pattern of digit placeholders: 0 for a digit that is always shown, # for
+/// one that is only shown if needed, a comma in the whole part to separate thousands and a period
+/// for the decimals. The text around the pattern is kept, and a % in it multiplies the number by
+/// 100.
+fn format_number(number: f64, format: &str) -> String {
+    let is_placeholder = |c: char| c == '0' || c == '#';
+    let (start, end) = match (format.find(is_placeholder), format.rfind(is_placeholder)) {
+        (Some(start), Some(end)) => (start, end + 1),
+        _ => return format.to_string(),
+    };
+    // A period right before the first placeholder belongs to the pattern, e.g. ".00".
+    let start = match format[..start].strip_suffix('.') {
+        Some(before) => before.len(),
+        None => start,
+    };
+    let (prefix, pattern, suffix) = (&format[..start], &format[start..end], &format[end..]);
+
+    let number = if format.contains('%') {
+        number * 100.0
+    } else {
+        number
+    };
+    let (whole_pattern, decimal_pattern) = pattern.split_once('.').unwrap_or((pattern, ""));
+    let decimals = decimal_pattern
+        .chars()
+        .filter(|c| is_placeholder(*c))
+        .count();
+    let min_decimals = decimal_pattern.chars().filter(|c| *c == '0').count();
+    let min_digits = whole_pattern.chars().filter(|c| *c == '0').count();
+
+    let rounded = format!("{:.*}", decimals, number.abs());
+    let (whole, fraction) = rounded.split_once('.').unwrap_or((&rounded, ""));
+    let whole = whole.trim_start_matches('0');
+    let whole = format!("{:0>1$}", whole, min_digits);
+    let whole = if whole_pattern.contains(',') {
+        group_thousands(&whole)
+    } else {
+        whole
+    };
+    let mut fraction = fraction.to_string();
+    while fraction.len() > min_decimals && fraction.ends_with('0') {
+        fraction.pop();
+    }
+
+    let is_negative = number < 0.0 && (whole.chars().chain(fraction.chars())).any(|c| c != '0');
+    format!(
+        "{}{}{}{}{}{}",
+        if is_negative { "-" } else { "" },
+        prefix,
+        whole,
+        if fraction.is_empty() { "" } else { "." },
+        fraction,
+        suffix
+    )
+}
+
+/// Separate the thousands of a whole number with commas, e.g. 1234567 becomes 1,234,567.
+fn group_thousands(digits: &str) -> String {
+    let mut out = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// Format a date with codes for its parts, case-insensitive: yyyy or yy for the year, m, mm, mmm
+/// (Jan) or mmmm (January) for the month, d, dd, ddd (Mon) or dddd (Monday) for the day and h, hh,
+/// m, mm, s or ss for the time. Like in spreadsheets, m is the minute right after an hour or right
+/// before a second. Other characters are kept as they are.
+fn format_date(serial: f64, format: &str) -> String {
+    let (year, month, day) = date::ymd(serial);
+    let seconds = ((serial - serial.floor()) * 86400.0).round() as u32;
+    let (hour, minute, second) = (seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+    let weekday = dates::day_of_week(serial) as usize;
+
+    // Split the format into runs of the same code letter and other text.
+    let chars: Vec<char> = format.chars().collect();
+    let mut tokens: Vec<(char, usize)> = Vec::new();
+    for c in chars {
+        let code = c.to_ascii_lowercase();
+        match tokens.last_mut() {
+            Some((last, len)) if "ymdhs".contains(code) && *last == code => *len += 1,
+            _ if "ymdhs".contains(code) => tokens.push((code, 1)),
+            _ => tokens.push((c, 0)),
+        }
+    }
+
+    let mut out = String::new();
+    for (i, &(code, len)) in tokens.iter().enumerate() {
+        let previous_code = tokens[..i].iter().rev().find(|(_, len)| *len > 0);
+        let next_code = tokens[i + 1..].iter().find(|(_, len)| *len > 0);
+        let is_minute = code == 'm'
+            && len <= 2
+            && (matches!(previous_code, Some(('h', _))) || matches!(next_code, Some(('s', _))));
+        let part = match (code, len) {
+            (c, 0) => c.to_string(),
+            ('y', 1..=2) => format!("{:02}", year.rem_euclid(100)),
+            ('y', _) => format!("{:04}", year),
+            ('m', _) if is_minute => format!("{:01$}", minute, len),
+            ('m', 1..=2) => format!("{:01$}", month, len),
+            ('m', 3) => MONTHS[month as usize - 1][..3].to_string(),
+            ('m', _) => MONTHS[month as usize - 1].to_string(),
+            ('d', 1..=2) => format!("{:01$}", day, len),
+            ('d', 3) => WEEKDAYS[weekday][..3].to_string(),
+            ('d', _) => WEEKDAYS[weekday].to_string(),
+            ('h', _) => format!("{:01$}", hour, len.min(2)),
+            (_, _) => format!("{:01$}", second, len.min(2)),
+        };
+        out.push_str(&part);
+    }
+    out
+}
+
+/// TRIM(text) removes the spaces at the start and end and keeps single spaces between words.
+fn trim(args: &[Argument]) -> Result<Value, CellError> {
+    let text = text(&args[0])?;
+    Ok(Value::Text(
+        text.split(' ')
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" "),
+    ))
+}
+
+/// UPPER(text)
+fn upper(args: &[Argument]) -> Result<Value, CellError> {
+    Ok(Value::Text(text(&args[0])?.to_uppercase()))
+}
+
+/// VALUE(text) reads a number from text, allowing spaces around it, commas between thousands, a
+/// percentage like 15% or an ISO date, which becomes its serial number.
+fn value(args: &[Argument]) -> Result<Value, CellError> {
+    let t = match args[0].value()? {
+        Value::Text(t) => t.trim(),
+        _ => return Ok(Value::Number(args[0].number()?)),
+    };
+    let number = t.replace(',', "");
+    parse_number(&number)
+        .or_else(|| parse_percent(&number))
+        .or_else(|| date::parse(t))
+        .map(Value::Number)
+        .ok_or_else(|| CellError::value(format!("'{}' is not a number.", t)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builtins::resolve;
+    use crate::builtins::text::{format_date, format_number, mid, substitute};
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
+    use crate::date;
+    use crate::function_registry::Argument;
+    use crate::value::Value;
+
+    fn text(t: &str) -> Cell {
+        Cell::Text(t.to_string())
+    }
+
+    #[test]
+    fn takes_parts_of_text() {
+        assert_eq!(
+            resolve(&[
+                "Grüße",
+                "=LEN(A1)",
+                "=LEFT(A1)",
+                "=LEFT(A1, 3)",
+                "=RIGHT(A1, 2)",
+                "=RIGHT(A1, 10)",
+                "=MID(A1, 2, 3)",
+                "=MID(A1, 9, 3)",
+                "=LEN(1234)",
+            ])[1..],
+            [
+                Cell::Number(5.0),
+                text("G"),
+                text("Grü"),
+                text("ße"),
+                text("Grüße"),
+                text("rüß"),
+                text(""),
+                Cell::Number(4.0),
+            ]
+        );
+        assert_eq!(
+            resolve(&["=MID(\"abc\", 0, 1)"])[0],
+            Cell::Error(CellError::value(
+                "The start of MID must be at least 1 but is 0.".to_string()
+            ))
+        );
+        assert_eq!(
+            resolve(&["=LEFT(\"abc\", -1)"])[0],
+            Cell::Error(CellError::value(
+                "LEFT expects a count of characters of at least 0 but got -1.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn rejects_positions_that_are_not_finite() {
+        let text = |t: &str| Argument::Value(Value::Text(t.to_string()));
+        let number = |n: f64| Argument::Value(Value::Number(n));
+        for n in [f64::NAN, f64::INFINITY, 0.0] {
+            assert_eq!(
+                mid(&[text("abc"), number(n), number(1.0)]),
+                Err(CellError::value(format!(
+                    "The start of MID must be at least 1 but is {}.",
+                    n
+                )))
+            );
+            assert_eq!(
+                substitute(&[text("aaa"), text("a"), text("b"), number(n)]),
+                Err(CellError::value(format!(
+                    "The instance of SUBSTITUTE must be at least 1 but is {}.",
+                    n
+                )))
+            );
+        }
+        assert_eq!(
+            mid(&[text("abc"), number(1.0), number(f64::NAN)]),
+            Err(CellError::value(
+                "MID expects a count of characters of at least 0 but got NaN.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn cleans_up_text() {
+        assert_eq!(
+            resolve(&[
+                "=UPPER(\"Acme Ltd.\")",
+                "=LOWER(\"Acme Ltd.\")",
+                "=TRIM(\"  Acme   Ltd. \")",
+                "=SUBSTITUTE(\"a-b-c\", \"-\", \"/\")",
+                "=SUBSTITUTE(\"a-b-c\", \"-\", \"\", 2)",
+                "=SUBSTITUTE(\"a-b-c\", \"-\", \"\", 3)",
+            ]),
+            [
+                text("ACME LTD."),
+                text("acme ltd."),
+                text("Acme Ltd."),
+                text("a/b/c"),
+                text("a-bc"),
+                text("a-b-c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_text() {
+        assert_eq!(
+            resolve(&[
+                "=SPLIT(\"SKU-123-XL\", \"-\", 2)",
+                "=SPLIT(\"SKU-123-XL\", \"-\", 3)",
+                "=SPLIT(\"SKU\", \"-\", 1)",
+                "=SPLIT(\"SKU-123\", \"-\", 3)",
+            ]),
+            [
+                text("123"),
+                text("XL"),
+                text("SKU"),
+                Cell::Error(CellError::value(
+                    "'SKU-123' has no part 3 when split at '-'.".to_string()
+                )),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_numbers_from_text() {
+        assert_eq!(
+            resolve(&[
+                "=VALUE(\" 42 \")",
+                "=VALUE(\"1,234.5\")",
+                "=VALUE(\"15%\")",
+                "=VALUE(\"2015-05-20\")",
+                "=VALUE(7)",
+                "=VALUE(\"n/a\")",
+            ]),
+            [
+                Cell::Number(42.0),
+                Cell::Number(1234.5),
+                Cell::Number(0.15),
+                Cell::Number(42144.0),
+                Cell::Number(7.0),
+                Cell::Error(CellError::value("'n/a' is not a number.".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_numbers() {
+        assert_eq!(format_number(1234.567, "0.00"), "1234.57");
+        assert_eq!(format_number(1234.567, "#,##0.00"), "1,234.57");
+        assert_eq!(format_number(1234567.0, "#,##0"), "1,234,567");
+        assert_eq!(format_number(0.5, "#.##"), ".5");
+        assert_eq!(format_number(0.5, "0.##"), "0.5");
+        assert_eq!(format_number(3.0, "000"), "003");
+        assert_eq!(format_number(0.155, "0.0%"), "15.5%");
+        assert_eq!(format_number(-12.5, "$#,##0.00"), "-$12.50");
+        assert_eq!(format_number(-0.001, "0.00"), "0.00");
+        assert_eq!(format_number(12.0, ".00"), "12.00");
+        assert_eq!(format_number(12.0, "none"), "none");
+    }
+
+    #[test]
+    fn formats_dates() {
+        let serial = date::serial(2015, 5, 3) + (13.0 * 3600.0 + 7.0 * 60.0 + 9.0) / 86400.0;
+        assert_eq!(format_date(serial, "yyyy-mm-dd"), "2015-05-03");
+        assert_eq!(format_date(serial, "DD/MM/YY"), "03/05/15");
+        assert_eq!(format_date(serial, "d mmm yyyy"), "3 May 2015");
+        assert_eq!(format_date(serial, "dddd, mmmm d"), "Sunday, May 3");
+        assert_eq!(format_date(serial, "ddd hh:mm:ss"), "Sun 13:07:09");
+        assert_eq!(format_date(serial, "h:mm"), "13:07");
+        assert_eq!(format_date(serial, "mm:ss"), "07:09");
+    }
+
+    #[test]
+    fn formats_values_as_text() {
+        assert_eq!(
+            resolve(&[
+                "2015-05-20",
+                "=TEXT(A1, \"dd.mm.yyyy\")",
+                "=TEXT(0.25, \"0%\")",
+                "=TEXT(\"2015-05-20\", \"yyyy\")",
+                "=TEXT(\"abc\", \"0.00\")",
+            ])[1..],
+            [
+                text("20.05.2015"),
+                text("25%"),
+                text("2015"),
+                Cell::Error(CellError::value("'abc' is not a number.".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_numbers_with_text_around_them() {
+        assert_eq!(
+            resolve(&[
+                "=TEXT(5, \"0 items\")",
+                "=TEXT(1234.5, \"#,##0.00 USD\")",
+                "=TEXT(0.5, \"0.0 hrs\")",
+                "=TEXT(3, \"days: 0\")",
+            ]),
+            [
+                text("5 items"),
+                text("1,234.50 USD"),
+                text("0.5 hrs"),
+                text("days: 3")
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_with_sections() {
+        assert_eq!(
+            resolve(&[
+                "=TEXT(-5, \"0;(0)\")",
+                "=TEXT(5, \"0;(0)\")",
+                "=TEXT(0, \"0;(0)\")",
+                "=TEXT(0, \"0.0;-0.0;zero\")",
+                "=TEXT(-1.5, \"0.0;-0.0;zero\")",
+                "=TEXT(42144, \"yyyy-mm-dd;negative\")",
+                "=TEXT(1, \"0;0;0;@\")",
+            ]),
+            [
+                text("(5)"),
+                text("5"),
+                text("0"),
+                text("zero"),
+                text("-1.5"),
+                text("2015-05-20"),
+                Cell::Error(CellError::value(
+                    "The format '0;0;0;@' has more than three sections.".to_string()
+                ))
+            ]
+        );
+    }
+}
@@ -1,39 +1,62 @@
 use std::fmt;
 
+use crate::cell_error::CellError;
+use crate::date;
 use crate::expression::Expression;
+use crate::function_registry::FunctionRegistry;
 use crate::sheet::Sheet;
+use crate::value::Value;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Cell {
+    Boolean(bool),
+    /// A date as serial number, see `date`.
+    Date(f64),
     Empty,
-    Error(String),
+    Error(CellError),
     Expression(Expression),
     Number(f64),
+    /// A number that was written as a percentage, e.g. 15% holds 0.15 and is displayed as 15%.
+    Percent(f64),
     Text(String),
 }
 
 impl Cell {
-    /// Resolve the Cell so it can be displayed. If expression, resolve the expression, otherwise simply
-    /// return the cell because the content can be displayed directly.
-    pub fn resolve(&self, sheet: &Sheet, resolved: &mut Sheet) {
+    /// Resolve the Cell at (row, col) to its value. If expression, resolve the expression, otherwise
+    /// simply return the content of the cell.
+    pub fn resolve(&self, row: usize, col: usize, resolved: &Sheet) -> Result<Value, CellError> {
         match self {
-            Cell::Expression(e) => e.resolve(sheet, resolved),
-            _ => (),
+            Cell::Boolean(b) => Ok(Value::Boolean(*b)),
+            Cell::Date(n) => Ok(Value::Date(*n)),
+            Cell::Empty => Ok(Value::Empty),
+            Cell::Error(e) => Err(e.clone()),
+            Cell::Expression(e) => e.resolve(row, col, resolved),
+            Cell::Number(n) | Cell::Percent(n) => Ok(Value::Number(*n)),
+            Cell::Text(t) => Ok(Value::Text(t.clone())),
         }
     }
 
-    pub fn parse(row: usize, col: usize, input: &str) -> Self {
+    pub fn parse(input: &str, registry: &FunctionRegistry) -> Self {
         let trimmed = input.trim();
         if let Some(first_char) = trimmed.chars().next() {
-            match first_char {
-                '^' | '<' | '>' | '=' => match Expression::parse(row, col, &trimmed[1..]) {
-                    Ok(expression) => Cell::Expression(expression),
-                    Err(e) => Cell::Error(e.to_string()),
-                },
-                _ => {
-                    // First try to parse as number
-                    if let Ok(num) = trimmed.parse::<f64>() {
+            let expression = match first_char {
+                '^' | '<' | '>' => Some(Expression::parse(trimmed, registry)),
+                '=' => Some(Expression::parse(&trimmed[1..], registry)),
+                _ => None,
+            };
+            match expression {
+                Some(Ok(expression)) => Cell::Expression(expression),
+                Some(Err(e)) => Cell::Error(CellError::new(e.kind, e.to_string())),
+                None => {
+                    // First try to parse as number, percentage, date or boolean
+                    if let Some(num) = parse_number(trimmed) {
                         Cell::Number(num)
+                    } else if let Some(num) = parse_percent(trimmed) {
+                        Cell::Percent(num)
+                    } else if let Some(serial) = date::parse(trimmed) {
+                        Cell::Date(serial)
+                    } else if let Some(b) = parse_boolean(trimmed) {
+                        Cell::Boolean(b)
                     } else {
                         // Else simply return text
                         Cell::Text(trimmed.to_string())
@@ -44,15 +67,55 @@ impl Cell {
             Cell::Empty
         }
     }
+
+    /// Parse a cell that was in quotes in CSV: it is text as it is, so quotes keep text like `15%`
+    /// or ` x ` from being read as something else. A formula is still a formula, as it needs quotes
+    /// when it contains the delimiter. A `'` in front makes the rest text, e.g. `'=A1`.
+    pub fn parse_quoted(input: &str, registry: &FunctionRegistry) -> Self {
+        if let Some(text) = input.strip_prefix('\'') {
+            Cell::Text(text.to_string())
+        } else if input.trim_start().starts_with('=') {
+            Cell::parse(input, registry)
+        } else {
+            Cell::Text(input.to_string())
+        }
+    }
+}
+
+/// Parse TRUE or FALSE, ignoring case.
+pub fn parse_boolean(input: &str) -> Option<bool> {
+    if input.eq_ignore_ascii_case("TRUE") {
+        Some(true)
+    } else if input.eq_ignore_ascii_case("FALSE") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Parse a percentage like 15% or 2.5 % into its fraction, 0.15 or 0.025.
+pub fn parse_percent(input: &str) -> Option<f64> {
+    let number = input.strip_suffix('%')?.trim_end();
+    parse_number(number).map(|n| n / 100.0)
+}
+
+/// Parse a finite number, so NaN and inf stay text.
+pub fn parse_number(input: &str) -> Option<f64> {
+    input.parse::<f64>().ok().filter(|n| n.is_finite())
 }
 
 impl fmt::Display for Cell {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Cell::Boolean(true) => write!(f, "TRUE"),
+            Cell::Boolean(false) => write!(f, "FALSE"),
+            Cell::Date(n) => write!(f, "{}", date::format(*n)),
             Cell::Empty => write!(f, ""),
-            Cell::Error(e) => write!(f, "#ERROR#: {}", e),
-            Cell::Expression(e) => write!(f, "{:?}", e),
+            Cell::Error(e) => write!(f, "{}", e),
+            Cell::Expression(e) => write!(f, "{}", e),
             Cell::Number(n) => write!(f, "{}", n),
+            // Round away the error of the multiplication, so 0.15 is shown as 15% instead of 15.000000000000002%.
+            Cell::Percent(n) => write!(f, "{}%", (n * 100.0 * 1e9).round() / 1e9),
             Cell::Text(t) => write!(f, "{}", t),
         }
     }
@@ -61,44 +124,88 @@ impl fmt::Display for Cell {
 #[cfg(test)]
 mod tests {
     use crate::cell::Cell;
+    use crate::cell_error::CellError;
     use crate::cell_pos::CellPos;
     use crate::cell_range::CellRange;
     use crate::expression::{Clone, Expression};
     use crate::formula::{ArithmeticOperator, Formula, Operator};
     use crate::function::Function;
-    use crate::number_or_cell_pos::NumberOrCellPos;
+    use crate::function_registry::FunctionRegistry;
+    use crate::sheet::Sheet;
+
+    fn parse(input: &str) -> Cell {
+        Cell::parse(input, &FunctionRegistry::default())
+    }
 
     #[test]
     fn parses_empty_cell() {
-        assert_eq!(Cell::parse(""), Cell::Empty);
+        assert_eq!(parse(""), Cell::Empty);
     }
 
     #[test]
     fn parses_text_cells() {
-        assert_eq!(Cell::parse("amount"), Cell::Text("amount".to_string()));
-        assert_eq!(Cell::parse("Coffee"), Cell::Text("Coffee".to_string()));
-        assert_eq!(Cell::parse("Total"), Cell::Text("Total".to_string()));
-        assert_eq!(
-            Cell::parse("total_price"),
-            Cell::Text("total_price".to_string())
-        );
-        assert_eq!(Cell::parse("#ERROR#"), Cell::Text("#ERROR#".to_string()));
+        assert_eq!(parse("amount"), Cell::Text("amount".to_string()));
+        assert_eq!(parse("Coffee"), Cell::Text("Coffee".to_string()));
+        assert_eq!(parse("Total"), Cell::Text("Total".to_string()));
+        assert_eq!(parse("total_price"), Cell::Text("total_price".to_string()));
+        assert_eq!(parse("#ERROR#"), Cell::Text("#ERROR#".to_string()));
+        assert_eq!(parse("NaN"), Cell::Text("NaN".to_string()));
+        assert_eq!(parse("-inf"), Cell::Text("-inf".to_string()));
+        assert_eq!(parse("infinity%"), Cell::Text("infinity%".to_string()));
+    }
+
+    #[test]
+    fn parses_boolean_cells() {
+        assert_eq!(parse("TRUE"), Cell::Boolean(true));
+        assert_eq!(parse(" false "), Cell::Boolean(false));
+        assert_eq!(parse("True"), Cell::Boolean(true));
+        assert_eq!(parse("TRUEISH"), Cell::Text("TRUEISH".to_string()));
+    }
+
+    #[test]
+    fn displays_boolean_cells() {
+        assert_eq!(Cell::Boolean(true).to_string(), "TRUE");
+        assert_eq!(Cell::Boolean(false).to_string(), "FALSE");
+    }
+
+    #[test]
+    fn displays_expression_cells() {
+        assert_eq!(parse("=SUM(A1:B2,1)*2").to_string(), "=SUM(A1:B2, 1) * 2");
+        assert_eq!(parse(" ^ ").to_string(), "^");
+    }
+
+    #[test]
+    fn parses_percent_cells() {
+        assert_eq!(parse("15%"), Cell::Percent(0.15));
+        assert_eq!(parse(" 2.5 % "), Cell::Percent(0.025));
+        assert_eq!(parse("-50%"), Cell::Percent(-0.5));
+        assert_eq!(parse("%"), Cell::Text("%".to_string()));
+        assert_eq!(parse("15%%"), Cell::Text("15%%".to_string()));
+    }
+
+    #[test]
+    fn displays_percent_cells() {
+        assert_eq!(parse("15%").to_string(), "15%");
+        assert_eq!(parse("2.5%").to_string(), "2.5%");
+        assert_eq!(parse("0.1%").to_string(), "0.1%");
+    }
+
+    #[test]
+    fn parses_date_cells() {
+        assert_eq!(parse("2015-05-20"), Cell::Date(42144.0));
+        assert_eq!(parse(" 2015-05-20T12:00 "), Cell::Date(42144.5));
+        assert_eq!(parse("2015-02-30"), Cell::Text("2015-02-30".to_string()));
+        assert_eq!(parse("2015-05-20").to_string(), "2015-05-20");
     }
 
     #[test]
     fn parses_clone_cells() {
-        assert_eq!(
-            Cell::parse("^"),
-            Cell::Expression(Expression::Clone(Clone::Top))
-        );
+        assert_eq!(parse("^"), Cell::Expression(Expression::Clone(Clone::Top)));
 
-        assert_eq!(
-            Cell::parse("<"),
-            Cell::Expression(Expression::Clone(Clone::Left))
-        );
+        assert_eq!(parse("<"), Cell::Expression(Expression::Clone(Clone::Left)));
 
         assert_eq!(
-            Cell::parse(">"),
+            parse(">"),
             Cell::Expression(Expression::Clone(Clone::Right))
         );
     }
@@ -106,20 +213,20 @@ mod tests {
     #[test]
     fn parses_formula_cells() {
         assert_eq!(
-            Cell::parse("=A1 + B2"),
+            parse("=A1 + B2"),
             Cell::Expression(Expression::Formula(Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Addition),
-                NumberOrCellPos::CellPos(CellPos::new("A1".to_string(), 1, 1)),
-                NumberOrCellPos::CellPos(CellPos::new("B2".to_string(), 2, 2))
+                Formula::CellPos(CellPos::new("A1".to_string(), 1, 1)),
+                Formula::CellPos(CellPos::new("B2".to_string(), 2, 2))
             )))
         );
 
         assert_eq!(
-            Cell::parse("=A1 - 1"),
+            parse("=A1 - 1"),
             Cell::Expression(Expression::Formula(Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Subtraction),
-                NumberOrCellPos::CellPos(CellPos::new("A1".to_string(), 1, 1)),
-                NumberOrCellPos::Number(1.0)
+                Formula::CellPos(CellPos::new("A1".to_string(), 1, 1)),
+                Formula::Number(1.0)
             )))
         );
     }
@@ -127,34 +234,65 @@ mod tests {
     #[test]
     fn parses_function_cells() {
         assert_eq!(
-            Cell::parse("=AVG(A1:A3)"),
-            Cell::Expression(Expression::Function(Function::Avg(CellRange::new(
-                "A1:A3".to_string(),
-                1,
-                1,
-                3,
-                1
+            parse("=AVG(A1:A3)"),
+            Cell::Expression(Expression::Formula(Formula::Function(Function::builtin(
+                "AVG",
+                vec![Formula::CellRange(CellRange::new(
+                    "A1:A3".to_string(),
+                    1,
+                    1,
+                    3,
+                    1
+                ))]
             ))))
         );
 
         assert_eq!(
-            Cell::parse("=SUM(D2:D4)"),
-            Cell::Expression(Expression::Function(Function::Sum(CellRange::new(
-                "D2:D4".to_string(),
-                2,
-                4,
-                4,
-                4
+            parse("=SUM(D2:D4)"),
+            Cell::Expression(Expression::Formula(Formula::Function(Function::builtin(
+                "SUM",
+                vec![Formula::CellRange(CellRange::new(
+                    "D2:D4".to_string(),
+                    2,
+                    4,
+                    4,
+                    4
+                ))]
             ))))
         );
     }
 
     #[test]
     fn parses_error_cells() {
-        println!("{:?}", Cell::parse("=nope + 1"));
-        println!("{:?}", Cell::parse("=IF(1, 2, 3)"));
-        println!("{:?}", Cell::parse("=LOOKUP(F4, B5:B9, C5:C9)"));
-        println!("{:?}", Cell::parse("=DATE(2015, 5, 20)"));
-        println!("{:?}", Cell::parse("=AVG(?)"));
+        assert_eq!(
+            parse("=nope + 1"),
+            Cell::Error(CellError::parse(
+                "'nope' is not a valid cell reference. (column 0)"
+            ))
+        );
+        assert_eq!(
+            parse("=AVG(?)"),
+            Cell::Error(CellError::parse("Unexpected character '?'. (column 4)"))
+        );
+        assert_eq!(
+            parse("=SUM(1, )"),
+            Cell::Error(CellError::parse("Unexpected ')'. (column 7)"))
+        );
+
+        // Unknown functions parse, but resolve to #NAME?.
+        let sheet = Sheet::parse_input(String::new(), &FunctionRegistry::default()).unwrap();
+        for (input, name) in [
+            ("=OOPS(1, 2, 3)", "OOPS"),
+            ("=XYZ(F4, B5:B9, C5:C9)", "XYZ"),
+            ("=XYZ(2015, 5, 20)", "XYZ"),
+        ] {
+            assert_eq!(
+                parse(input).resolve(0, 0, &sheet),
+                Err(CellError::name(format!(
+                    "Function '{}' not supported.",
+                    name
+                )))
+            );
+        }
     }
 }
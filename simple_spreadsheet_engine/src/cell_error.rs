@@ -0,0 +1,161 @@
+use std::fmt;
+
+use crate::cell_pos::CellPos;
+
+/// The kind of an error, which matches the error values of spreadsheets where they have one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorKind {
+    /// #CYCLE!: The cell is part of a circular reference.
+    Cycle,
+    /// #DIV/0!: A number is divided by zero.
+    DivisionByZero,
+    /// #NAME?: A formula uses a name that doesn't exist, e.g. a misspelled function.
+    Name,
+    /// #N/A: A value that was looked for could not be found, e.g. no condition of IFS holds.
+    NotAvailable,
+    /// #NUM!: A calculation has no valid result, e.g. an iterative solver did not converge.
+    Num,
+    /// #ERROR#: The content of the cell could not be parsed.
+    Parse,
+    /// #REF!: A reference points to a cell that doesn't exist.
+    Reference,
+    /// #VALUE!: An operand or argument has the wrong type, e.g. text in an arithmetic operation.
+    Value,
+}
+
+impl ErrorKind {
+    /// The error value as shown in a cell.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            ErrorKind::Cycle => "#CYCLE!",
+            ErrorKind::DivisionByZero => "#DIV/0!",
+            ErrorKind::Name => "#NAME?",
+            ErrorKind::NotAvailable => "#N/A",
+            ErrorKind::Num => "#NUM!",
+            ErrorKind::Parse => "#ERROR#",
+            ErrorKind::Reference => "#REF!",
+            ErrorKind::Value => "#VALUE!",
+        }
+    }
+
+    /// The number ERROR.TYPE gives for this kind, the same as in spreadsheets. Cycles and parse errors
+    /// have no number, since spreadsheets don't accept such formulas.
+    pub fn code(&self) -> Option<u8> {
+        match self {
+            ErrorKind::DivisionByZero => Some(2),
+            ErrorKind::Value => Some(3),
+            ErrorKind::Reference => Some(4),
+            ErrorKind::Name => Some(5),
+            ErrorKind::Num => Some(6),
+            ErrorKind::NotAvailable => Some(7),
+            ErrorKind::Cycle | ErrorKind::Parse => None,
+        }
+    }
+}
+
+/// The error a cell resolves to: its kind, a message that explains it and, if it comes from another
+/// cell through a reference, the cell it originated in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellError {
+    pub kind: ErrorKind,
+    pub detail: String,
+    /// The cell where the error occurred, None if it occurred in the cell that holds it.
+    pub origin: Option<CellPos>,
+}
+
+impl CellError {
+    pub fn new(kind: ErrorKind, detail: impl Into<String>) -> Self {
+        CellError {
+            kind,
+            detail: detail.into(),
+            origin: None,
+        }
+    }
+
+    /// The error of a cell on a circular reference, with the path of references that leads from the
+    /// cell back to itself, e.g. A1 -> B1 -> A1.
+    pub fn cycle(path: Vec<CellPos>) -> Self {
+        let path: Vec<&str> = path.iter().map(|pos| pos.str.as_str()).collect();
+        CellError::new(ErrorKind::Cycle, path.join(" -> "))
+    }
+
+    pub fn division_by_zero(detail: impl Into<String>) -> Self {
+        CellError::new(ErrorKind::DivisionByZero, detail)
+    }
+
+    pub fn name(detail: impl Into<String>) -> Self {
+        CellError::new(ErrorKind::Name, detail)
+    }
+
+    pub fn not_available(detail: impl Into<String>) -> Self {
+        CellError::new(ErrorKind::NotAvailable, detail)
+    }
+
+    pub fn num(detail: impl Into<String>) -> Self {
+        CellError::new(ErrorKind::Num, detail)
+    }
+
+    pub fn parse(detail: impl Into<String>) -> Self {
+        CellError::new(ErrorKind::Parse, detail)
+    }
+
+    pub fn reference(detail: impl Into<String>) -> Self {
+        CellError::new(ErrorKind::Reference, detail)
+    }
+
+    pub fn value(detail: impl Into<String>) -> Self {
+        CellError::new(ErrorKind::Value, detail)
+    }
+
+    /// The error as read from the cell at `origin`. Keeps the origin of an error that was already
+    /// read from another cell, so it always points to the cell where the error occurred.
+    pub fn with_origin(self, origin: CellPos) -> Self {
+        CellError {
+            origin: self.origin.or(Some(origin)),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for CellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.symbol(), self.detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cell_error::{CellError, ErrorKind};
+    use crate::cell_pos::CellPos;
+
+    #[test]
+    fn displays_cycle_path() {
+        assert_eq!(
+            CellError::cycle(vec![
+                CellPos::new("A1".to_string(), 1, 1),
+                CellPos::new("B1".to_string(), 1, 2),
+                CellPos::new("A1".to_string(), 1, 1),
+            ])
+            .to_string(),
+            "#CYCLE!: A1 -> B1 -> A1"
+        );
+    }
+
+    #[test]
+    fn displays_parse_error() {
+        assert_eq!(
+            CellError::parse("Unsupported expression.").to_string(),
+            "#ERROR#: Unsupported expression."
+        );
+    }
+
+    #[test]
+    fn keeps_first_origin() {
+        let error = CellError::division_by_zero("Cannot divide 1 by zero.")
+            .with_origin(CellPos::from_index(0, 0))
+            .with_origin(CellPos::from_index(0, 1));
+        assert_eq!(error.kind, ErrorKind::DivisionByZero);
+        assert_eq!(error.origin, Some(CellPos::from_index(0, 0)));
+        assert_eq!(error.to_string(), "#DIV/0!: Cannot divide 1 by zero.");
+    }
+}
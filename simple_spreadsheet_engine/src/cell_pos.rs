@@ -1,22 +1,92 @@
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct CellPos {
     pub str: String,
     pub row: usize,
     pub col: usize,
+    /// The row is anchored with $, e.g. A$1, and doesn't move when the position is shifted.
+    pub row_absolute: bool,
+    /// The column is anchored with $, e.g. $A1, and doesn't move when the position is shifted.
+    pub col_absolute: bool,
 }
 
 impl CellPos {
+    /// The anchored axes are read from the $ signs in str, e.g. $A$1 anchors both.
     pub fn new(str: String, row: usize, col: usize) -> Self {
-        CellPos { str, row, col }
+        let col_absolute = str.starts_with('$');
+        let row_absolute = str[col_absolute as usize..].contains('$');
+        CellPos {
+            str,
+            row,
+            col,
+            row_absolute,
+            col_absolute,
+        }
+    }
+
+    /// Create the CellPos of the zero-based (row, col) index in `Sheet::cells`, e.g. (0, 27) is AB1.
+    pub fn from_index(row: usize, col: usize) -> Self {
+        let mut name = String::new();
+        let mut column = col + 1;
+        while column > 0 {
+            let remainder = (column - 1) % 26;
+            name.insert(0, (b'A' + remainder as u8) as char);
+            column = (column - 1) / 26;
+        }
+        name.push_str(&(row + 1).to_string());
+        CellPos::new(name, row + 1, col + 1)
+    }
+
+    /// Like `from_index`, with $ signs before the anchored axes, e.g. (0, 0, true, false) is A$1.
+    pub fn anchored(row: usize, col: usize, row_absolute: bool, col_absolute: bool) -> Self {
+        let pos = CellPos::from_index(row, col);
+        let digits = pos.str.find(|c: char| c.is_ascii_digit()).unwrap();
+        let (column, row) = pos.str.split_at(digits);
+        CellPos::new(
+            format!(
+                "{}{}{}{}",
+                if col_absolute { "$" } else { "" },
+                column,
+                if row_absolute { "$" } else { "" },
+                row
+            ),
+            pos.row,
+            pos.col,
+        )
+    }
+
+    /// Zero-based (row, col) index of this position in `Sheet::cells`.
+    pub fn index(&self) -> (usize, usize) {
+        (self.row - 1, self.col - 1)
     }
 
+    /// The position moved by a number of rows and columns, None if it moves above or left of A1.
+    /// Anchored axes don't move, e.g. $A1 shifted by one row and column is $A2.
+    pub fn shift(&self, rows: isize, cols: isize) -> Option<Self> {
+        let (mut row, mut col) = self.index();
+        if !self.row_absolute {
+            row = row.checked_add_signed(rows)?;
+        }
+        if !self.col_absolute {
+            col = col.checked_add_signed(cols)?;
+        }
+        Some(CellPos::anchored(
+            row,
+            col,
+            self.row_absolute,
+            self.col_absolute,
+        ))
+    }
+
+    /// Parse a position like B3, with $ before the column and/or the row to anchor them, e.g. $B$3.
     pub fn parse(input: &str) -> Result<Self, String> {
         // TODO: validate and split with regex instead -- https://crates.io/crates/regex
         match input.find(|c: char| c.is_ascii_digit()) {
             Some(i) => {
                 if let Ok(row) = input[i..].parse::<usize>() {
+                    let column_name = input[..i].strip_prefix('$').unwrap_or(&input[..i]);
+                    let column_name = column_name.strip_suffix('$').unwrap_or(column_name);
                     let mut column: usize = 0;
-                    for c in input[..i].chars() {
+                    for c in column_name.chars() {
                         column *= 26;
                         column += match c {
                             'A'..='Z' => c as usize - 'A' as usize + 1,
@@ -122,6 +192,96 @@ mod tests {
         );
     }
 
+    #[test]
+    fn can_create_from_index() {
+        assert_eq!(
+            CellPos::from_index(0, 0),
+            CellPos::new("A1".to_string(), 1, 1)
+        );
+        assert_eq!(
+            CellPos::from_index(8, 25),
+            CellPos::new("Z9".to_string(), 9, 26)
+        );
+        assert_eq!(
+            CellPos::from_index(233, 27),
+            CellPos::new("AB234".to_string(), 234, 28)
+        );
+        assert_eq!(
+            CellPos::from_index(99, 676),
+            CellPos::new("ZA100".to_string(), 100, 677)
+        );
+        assert_eq!(
+            CellPos::from_index(0, 18277),
+            CellPos::new("ZZZ1".to_string(), 1, 18278)
+        );
+    }
+
+    #[test]
+    fn can_create_anchored() {
+        assert_eq!(
+            CellPos::anchored(2, 27, true, true),
+            CellPos::new("$AB$3".to_string(), 3, 28)
+        );
+        assert_eq!(CellPos::anchored(0, 0, true, false).str, "A$1");
+        assert_eq!(CellPos::anchored(0, 0, false, true).str, "$A1");
+    }
+
+    #[test]
+    fn can_shift() {
+        let pos = CellPos::new("B2".to_string(), 2, 2);
+        assert_eq!(
+            pos.shift(1, 24),
+            Some(CellPos::new("Z3".to_string(), 3, 26))
+        );
+        assert_eq!(
+            pos.shift(-1, -1),
+            Some(CellPos::new("A1".to_string(), 1, 1))
+        );
+        assert_eq!(pos.shift(-2, 0), None);
+        assert_eq!(pos.shift(0, -2), None);
+    }
+
+    #[test]
+    fn can_parse_absolute_references() {
+        let pos = CellPos::parse("$B$3").unwrap();
+        assert_eq!((pos.row, pos.col), (3, 2));
+        assert!(pos.row_absolute && pos.col_absolute);
+
+        let pos = CellPos::parse("$b3").unwrap();
+        assert_eq!((pos.row, pos.col), (3, 2));
+        assert!(!pos.row_absolute && pos.col_absolute);
+
+        let pos = CellPos::parse("AB$30").unwrap();
+        assert_eq!((pos.row, pos.col), (30, 28));
+        assert!(pos.row_absolute && !pos.col_absolute);
+
+        assert_eq!(
+            CellPos::parse("$A$1"),
+            Ok(CellPos::new("$A$1".to_string(), 1, 1))
+        );
+        assert!(!CellPos::parse("B3").unwrap().row_absolute);
+    }
+
+    #[test]
+    fn keeps_anchored_axes_when_shifted() {
+        let shift = |pos: &str, rows, cols| {
+            CellPos::parse(pos)
+                .unwrap()
+                .shift(rows, cols)
+                .map(|pos| pos.str)
+        };
+        assert_eq!(shift("$B$2", 3, 4), Some("$B$2".to_string()));
+        assert_eq!(shift("$B2", 3, 4), Some("$B5".to_string()));
+        assert_eq!(shift("B$2", 3, 4), Some("F$2".to_string()));
+        assert_eq!(shift("$B2", -1, -5), Some("$B1".to_string()));
+        assert_eq!(shift("B$2", -5, 1), Some("C$2".to_string()));
+        assert_eq!(shift("$B2", -2, 0), None);
+        assert_eq!(
+            CellPos::parse("$B2").unwrap().shift(1, 1),
+            Some(CellPos::new("$B3".to_string(), 3, 2))
+        );
+    }
+
     #[test]
     fn handles_unexpected_character() {
         assert_eq!(
@@ -136,6 +296,14 @@ mod tests {
             CellPos::parse("=XYZ123"),
             Err("Unexpected character '='.".to_string())
         );
+        assert_eq!(
+            CellPos::parse("$$A1"),
+            Err("Unexpected character '$'.".to_string())
+        );
+        assert_eq!(
+            CellPos::parse("A$$1"),
+            Err("Unexpected character '$'.".to_string())
+        );
     }
 
     #[test]
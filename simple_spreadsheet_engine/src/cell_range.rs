@@ -1,6 +1,6 @@
-use crate::{cell::Cell, cell_pos::CellPos, sheet::Sheet};
+use crate::{cell_error::CellError, cell_pos::CellPos, sheet::Sheet, value::Value};
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct CellRange {
     pub str: String,
     pub start_cell: CellPos,
@@ -24,16 +24,21 @@ impl CellRange {
         }
     }
 
+    /// Parse a range like A1:B3. A range with its corners the other way around, e.g. B3:A1, is
+    /// turned into the range from its top left to its bottom right corner.
     pub fn parse(input: &str) -> Result<Self, String> {
-        // TODO: don't allow invalid ranges
-        // TODO: support absolute reference with $
         match input.split(':').collect::<Vec<&str>>()[..] {
             [lhs, rhs] => match (CellPos::parse(lhs), CellPos::parse(rhs)) {
-                (Ok(start_cell), Ok(end_cell)) => Ok(CellRange {
-                    str: lhs.to_owned() + ":" + rhs,
-                    start_cell,
-                    end_cell,
-                }),
+                (Ok(start_cell), Ok(end_cell))
+                    if start_cell.row <= end_cell.row && start_cell.col <= end_cell.col =>
+                {
+                    Ok(CellRange {
+                        str: lhs.to_owned() + ":" + rhs,
+                        start_cell,
+                        end_cell,
+                    })
+                }
+                (Ok(a), Ok(b)) => Ok(CellRange::between(&a, &b)),
                 (Ok(_), Err(e)) => Err(format!("Right side is not a valid cell range: {}", e)),
                 (Err(e), Ok(_)) => Err(format!("Left side is not a valid cell range: {}", e)),
                 (Err(e1), Err(e2)) => Err(format!(
@@ -45,19 +50,64 @@ impl CellRange {
         }
     }
 
-    /// Return a vector of numbers in cells that lie in the specified range.
-    /// Only works for cells that contain a number.
-    /// TODO: resolve values through a dependency graph to ensure all references can resolve successfully?
-    pub(crate) fn resolve(&self, sheet: &Sheet) -> Vec<f64> {
-        let mut out = Vec::new();
-        for i in self.start_cell.row..=self.end_cell.row {
-            for j in self.start_cell.col..=self.end_cell.col {
-                if let Cell::Number(n) = sheet.cells[i - 1][j - 1] {
-                    out.push(n)
-                }
-            }
+    /// The range from the top left to the bottom right of two opposite corners. Each axis keeps the
+    /// anchor it has in the corner it comes from.
+    fn between(a: &CellPos, b: &CellPos) -> Self {
+        let (top, bottom) = if a.row <= b.row { (a, b) } else { (b, a) };
+        let (left, right) = if a.col <= b.col { (a, b) } else { (b, a) };
+        let start_cell = CellPos::anchored(
+            top.row - 1,
+            left.col - 1,
+            top.row_absolute,
+            left.col_absolute,
+        );
+        let end_cell = CellPos::anchored(
+            bottom.row - 1,
+            right.col - 1,
+            bottom.row_absolute,
+            right.col_absolute,
+        );
+        CellRange {
+            str: format!("{}:{}", start_cell.str, end_cell.str),
+            start_cell,
+            end_cell,
         }
-        out
+    }
+
+    /// The range moved by a number of rows and columns, None if it moves above or left of A1.
+    pub fn shift(&self, rows: isize, cols: isize) -> Option<Self> {
+        let start_cell = self.start_cell.shift(rows, cols)?;
+        let end_cell = self.end_cell.shift(rows, cols)?;
+        Some(CellRange {
+            str: format!("{}:{}", start_cell.str, end_cell.str),
+            start_cell,
+            end_cell,
+        })
+    }
+
+    /// Zero-based (row, col) indices of the cells that lie in the range and in a sheet of
+    /// (rows, columns) size, in row-major order. Cells beyond the sheet are left out, so a range
+    /// like A1:A20000000 only yields the cells that exist.
+    pub fn positions(&self, size: (usize, usize)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let (start_row, start_col) = self.start_cell.index();
+        let (end_row, end_col) = self.end_cell.index();
+        let (end_row, end_col) = ((end_row + 1).min(size.0), (end_col + 1).min(size.1));
+        (start_row..end_row).flat_map(move |i| (start_col..end_col).map(move |j| (i, j)))
+    }
+
+    /// The values of the cells in the range, row by row. The first error in the range is returned
+    /// instead. Cells are read from the resolved sheet, so formulas in the range must have been
+    /// evaluated first (see `DependencyGraph`).
+    pub(crate) fn values(&self, resolved: &Sheet) -> Result<Vec<Vec<Value>>, CellError> {
+        let (start_row, start_col) = self.start_cell.index();
+        let (end_row, end_col) = self.end_cell.index();
+        (start_row..=end_row)
+            .map(|i| {
+                (start_col..=end_col)
+                    .map(|j| resolved.value(i, j))
+                    .collect()
+            })
+            .collect()
     }
 }
 
@@ -86,6 +136,40 @@ mod tests {
             CellRange::parse("AA999:AAA1000").unwrap(),
             CellRange::new("AA999:AAA1000".to_string(), 999, 27, 1000, 703)
         );
+        assert_eq!(
+            CellRange::parse("$A$1:B$3").unwrap(),
+            CellRange::new("$A$1:B$3".to_string(), 1, 1, 3, 2)
+        );
+    }
+
+    #[test]
+    fn normalizes_reversed_ranges() {
+        assert_eq!(
+            CellRange::parse("B3:A1").unwrap(),
+            CellRange::new("A1:B3".to_string(), 1, 1, 3, 2)
+        );
+        assert_eq!(
+            CellRange::parse("A3:b1").unwrap(),
+            CellRange::new("A1:B3".to_string(), 1, 1, 3, 2)
+        );
+        assert_eq!(
+            CellRange::parse("$B1:A$3").unwrap(),
+            CellRange::new("A1:$B$3".to_string(), 1, 1, 3, 2)
+        );
+    }
+
+    #[test]
+    fn keeps_anchored_axes_when_shifted() {
+        let range = CellRange::parse("$A$1:A$3").unwrap();
+        assert_eq!(
+            range.shift(2, 1),
+            Some(CellRange::new("$A$1:B$3".to_string(), 1, 1, 3, 2))
+        );
+        let range = CellRange::parse("$A1:$A3").unwrap();
+        assert_eq!(
+            range.shift(2, 1),
+            Some(CellRange::new("$A3:$A5".to_string(), 3, 1, 5, 1))
+        );
     }
 
     #[test]
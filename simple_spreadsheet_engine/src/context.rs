@@ -0,0 +1,27 @@
+use crate::date;
+
+/// Settings that affect how formulas are evaluated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Context {
+    /// Serial number of the date that TODAY() returns.
+    pub today: f64,
+}
+
+impl Context {
+    /// A context in which TODAY() returns the given date, so results don't depend on when the sheet
+    /// is evaluated.
+    pub fn with_today(year: i64, month: u32, day: u32) -> Self {
+        Context {
+            today: date::serial(year, month as i64, day as i64),
+        }
+    }
+}
+
+impl Default for Context {
+    /// A context in which TODAY() returns the current date.
+    fn default() -> Self {
+        Context {
+            today: date::today(),
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use std::fmt;
+
+use crate::cell::Cell;
+use crate::function_registry::FunctionRegistry;
+
+/// How the cells of a sheet are written in CSV.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CsvOptions {
+    /// The character between the cells of a row.
+    pub delimiter: char,
+    /// The character around a cell that contains the delimiter, a line break or the quote itself,
+    /// which is written twice inside the cell.
+    pub quote: char,
+    /// How `write` lays out the cells, reading accepts both layouts.
+    pub layout: CsvLayout,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: ',',
+            quote: '"',
+            layout: CsvLayout::default(),
+        }
+    }
+}
+
+/// How the cells of a row are laid out around the delimiter.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum CsvLayout {
+    /// Only the delimiter between the cells, as in RFC 4180.
+    #[default]
+    Canonical,
+    /// Cells padded to the width of their column, numbers aligned right and other cells left, so the
+    /// columns line up in a text editor.
+    Aligned,
+}
+
+/// A cell as read from CSV: its text and whether it was in quotes, see `Cell::parse_quoted`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Field {
+    pub text: String,
+    pub quoted: bool,
+}
+
+/// Error while reading CSV, with the line and column (both starting at 1) where it occurred.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (line {}, column {})",
+            self.message, self.line, self.column
+        )
+    }
+}
+
+/// Read the rows of cells from CSV as described in RFC 4180: rows end with LF or CRLF and a cell
+/// in quotes can contain the delimiter, line breaks and doubled quotes. A byte order mark at the
+/// start is skipped.
+/// A formula that contains the delimiter needs quotes too, e.g. `"=SUM(A1, B1)"`. Spaces around a
+/// quoted cell are ignored.
+pub fn read(input: &str, options: &CsvOptions) -> Result<Vec<Vec<Field>>, CsvError> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut reader = Reader {
+        chars: input.chars().collect(),
+        position: 0,
+        line: 1,
+        column: 1,
+        options,
+    };
+
+    let mut rows = Vec::new();
+    while reader.peek(0).is_some() {
+        rows.push(reader.row()?);
+    }
+    Ok(rows)
+}
+
+/// Write the rows of cells as CSV, laid out as in the options. A cell that contains the delimiter,
+/// the quote or a line break is put in quotes, and so is text that would otherwise be read as
+/// another cell, such as `15%`, ` x ` or `=A1`, so `Sheet::parse_csv` gives the same cells back.
+pub fn write(rows: &[Vec<Cell>], options: &CsvOptions) -> String {
+    let rows: Vec<Vec<(String, bool)>> = rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| (quote(cell, options), is_aligned_right(cell)))
+                .collect()
+        })
+        .collect();
+
+    let mut widths = Vec::new();
+    if options.layout == CsvLayout::Aligned {
+        for row in rows.iter() {
+            widths.resize(widths.len().max(row.len()), 0);
+            for (j, (text, _)) in row.iter().enumerate() {
+                widths[j] = widths[j].max(text.chars().count());
+            }
+        }
+    }
+
+    let delimiter = options.delimiter.to_string();
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(j, (text, right))| {
+                    let width = widths.get(j).copied().unwrap_or(0);
+                    if *right {
+                        format!("{:>width$}", text)
+                    } else {
+                        format!("{:<width$}", text)
+                    }
+                })
+                .collect();
+            cells.join(&delimiter)
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Numbers, percentages and dates are aligned right in the aligned layout, like in spreadsheets.
+fn is_aligned_right(cell: &Cell) -> bool {
+    matches!(cell, Cell::Date(_) | Cell::Number(_) | Cell::Percent(_))
+}
+
+/// The cell as text, in quotes if it can't be read back as the same cell without them. Quoted text
+/// that starts with `=` or `'` gets a `'` in front, so it isn't read back as a formula.
+fn quote(cell: &Cell, options: &CsvOptions) -> String {
+    let quote = options.quote;
+    let mut text = cell.to_string();
+    let is_other_cell = match cell {
+        Cell::Text(t) => Cell::parse(t, &FunctionRegistry::empty()) != *cell,
+        _ => false,
+    };
+    if is_other_cell || text.contains([options.delimiter, quote, '\n', '\r']) {
+        if matches!(cell, Cell::Text(t) if t.trim_start().starts_with('=') || t.starts_with('\'')) {
+            text.insert(0, '\'');
+        }
+        let escaped = text.replace(quote, &quote.to_string().repeat(2));
+        format!("{}{}{}", quote, escaped, quote)
+    } else {
+        text
+    }
+}
+
+/// Join the cells into a row of CSV with the default options, in quotes if they contain the
+/// delimiter, so tests can write formulas like `=SUM(A1, B1)` as they are.
+#[cfg(test)]
+pub fn join(cells: &[&str]) -> String {
+    let cells: Vec<String> = cells
+        .iter()
+        .map(|cell| {
+            if cell.contains(',') {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.to_string()
+            }
+        })
+        .collect();
+    cells.join(",")
+}
+
+struct Reader<'a> {
+    chars: Vec<char>,
+    position: usize,
+    line: usize,
+    column: usize,
+    options: &'a CsvOptions,
+}
+
+impl Reader<'_> {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.position + offset).copied()
+    }
+
+    /// Move to the next character, keeping track of the line and column.
+    fn advance(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    /// The length of the line break at the position, 0 if there is none.
+    fn line_break(&self) -> usize {
+        match (self.peek(0), self.peek(1)) {
+            (Some('\n'), _) => 1,
+            (Some('\r'), Some('\n')) => 2,
+            _ => 0,
+        }
+    }
+
+    fn is_blank(&self, c: char) -> bool {
+        (c == ' ' || c == '\t') && c != self.options.delimiter
+    }
+
+    fn error(&self, message: String, line: usize, column: usize) -> CsvError {
+        CsvError {
+            message,
+            line,
+            column,
+        }
+    }
+
+    /// Read the cells up to and including the line break that ends the row.
+    fn row(&mut self) -> Result<Vec<Field>, CsvError> {
+        let mut cells = vec![self.cell()?];
+        while self.peek(0) == Some(self.options.delimiter) {
+            self.advance();
+            cells.push(self.cell()?);
+        }
+        for _ in 0..self.line_break() {
+            self.advance();
+        }
+        Ok(cells)
+    }
+
+    /// Read a cell up to the delimiter or line break that ends it.
+    fn cell(&mut self) -> Result<Field, CsvError> {
+        let blanks = (0..)
+            .take_while(|&i| self.peek(i).is_some_and(|c| self.is_blank(c)))
+            .count();
+        if self.peek(blanks) == Some(self.options.quote) {
+            for _ in 0..blanks {
+                self.advance();
+            }
+            Ok(Field {
+                text: self.quoted_cell()?,
+                quoted: true,
+            })
+        } else {
+            Ok(Field {
+                text: self.unquoted_cell(),
+                quoted: false,
+            })
+        }
+    }
+
+    fn quoted_cell(&mut self) -> Result<String, CsvError> {
+        let (line, column) = (self.line, self.column);
+        let quote = self.options.quote;
+        self.advance();
+
+        let mut cell = String::new();
+        loop {
+            match self.advance() {
+                None => {
+                    return Err(self.error("Quoted cell is not closed.".to_string(), line, column))
+                }
+                Some(c) if c == quote && self.peek(0) == Some(quote) => {
+                    self.advance();
+                    cell.push(quote);
+                }
+                Some(c) if c == quote => break,
+                Some(c) => cell.push(c),
+            }
+        }
+
+        while self.peek(0).is_some_and(|c| self.is_blank(c)) {
+            self.advance();
+        }
+        match self.peek(0) {
+            Some(c) if c != self.options.delimiter && self.line_break() == 0 => Err(self.error(
+                format!("Unexpected '{}' after quoted cell.", c),
+                self.line,
+                self.column,
+            )),
+            _ => Ok(cell),
+        }
+    }
+
+    fn unquoted_cell(&mut self) -> String {
+        let start = self.position;
+        while self
+            .peek(0)
+            .is_some_and(|c| c != self.options.delimiter && self.line_break() == 0)
+        {
+            self.advance();
+        }
+
+        self.chars[start..self.position].iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cell::Cell;
+    use crate::csv::{read, write, CsvError, CsvLayout, CsvOptions, Field};
+    use crate::function_registry::FunctionRegistry;
+    use crate::sheet::Sheet;
+
+    fn texts(rows: Vec<Vec<Field>>) -> Vec<Vec<String>> {
+        rows.into_iter()
+            .map(|row| row.into_iter().map(|field| field.text).collect())
+            .collect()
+    }
+
+    fn rows(input: &str) -> Vec<Vec<String>> {
+        texts(read(input, &CsvOptions::default()).unwrap())
+    }
+
+    fn strings(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn reads_rows_and_cells() {
+        assert_eq!(
+            rows("a,b, c\n1\n\n,2,\n"),
+            strings(&[&["a", "b", " c"], &["1"], &[""], &["", "2", ""]])
+        );
+        assert_eq!(rows("a,b\r\nc,d\r\n"), strings(&[&["a", "b"], &["c", "d"]]));
+        assert_eq!(rows("\u{feff}a,b"), strings(&[&["a", "b"]]));
+        assert_eq!(rows(""), strings(&[]));
+    }
+
+    #[test]
+    fn reads_quoted_cells() {
+        assert_eq!(
+            rows("\"a, b\",\"say \"\"hi\"\"\", \"=SUM(A1, B1)\" ,\"\"\n\"two\r\nlines\",x"),
+            strings(&[
+                &["a, b", "say \"hi\"", "=SUM(A1, B1)", ""],
+                &["two\r\nlines", "x"]
+            ])
+        );
+        let quoted: Vec<bool> = read("\"a\", b,\"\"", &CsvOptions::default()).unwrap()[0]
+            .iter()
+            .map(|field| field.quoted)
+            .collect();
+        assert_eq!(quoted, [true, false, true]);
+    }
+
+    #[test]
+    fn ends_unquoted_cells_at_the_delimiter() {
+        assert_eq!(
+            rows("1, =SUM(A1, B1), \"=SUM(A1, B1)\", =\"a\" & B1\n(x, y)"),
+            strings(&[
+                &["1", " =SUM(A1", " B1)", "=SUM(A1, B1)", " =\"a\" & B1"],
+                &["(x", " y)"]
+            ])
+        );
+    }
+
+    #[test]
+    fn reads_other_delimiters_and_quotes() {
+        let options = CsvOptions {
+            delimiter: ';',
+            quote: '\'',
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            read(
+                "1,5;'a; ''b''';'=ROUND(A1; 0)'\n'=IFERROR(A6, \"Oops!\")'",
+                &options
+            )
+            .map(texts),
+            Ok(strings(&[
+                &["1,5", "a; 'b'", "=ROUND(A1; 0)"],
+                &["=IFERROR(A6, \"Oops!\")"]
+            ]))
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_position() {
+        let error = |message: &str, line, column| {
+            Err(CsvError {
+                message: message.to_string(),
+                line,
+                column,
+            })
+        };
+        assert_eq!(
+            read("a,b\nc, \"d", &CsvOptions::default()),
+            error("Quoted cell is not closed.", 2, 4)
+        );
+        assert_eq!(
+            read("a,\"b\"c\n", &CsvOptions::default()),
+            error("Unexpected 'c' after quoted cell.", 1, 6)
+        );
+    }
+
+    fn sample() -> Vec<Vec<Cell>> {
+        vec![
+            vec![
+                Cell::Text("item".to_string()),
+                Cell::Text("price".to_string()),
+                Cell::Text("note".to_string()),
+            ],
+            vec![
+                Cell::Text("Tea".to_string()),
+                Cell::Number(2.5),
+                Cell::Text("green, \"loose\"".to_string()),
+            ],
+            vec![Cell::Text("Water".to_string()), Cell::Percent(0.15)],
+            vec![Cell::Empty, Cell::Number(12.25), Cell::Boolean(true)],
+        ]
+    }
+
+    #[test]
+    fn writes_canonical_csv() {
+        assert_eq!(
+            write(&sample(), &CsvOptions::default()),
+            "item,price,note\nTea,2.5,\"green, \"\"loose\"\"\"\nWater,15%\n,12.25,TRUE"
+        );
+        let options = CsvOptions {
+            delimiter: ';',
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            write(&[vec![Cell::Text("a, b; c".to_string())]], &options),
+            "\"a, b; c\""
+        );
+    }
+
+    #[test]
+    fn writes_aligned_columns() {
+        let options = CsvOptions {
+            layout: CsvLayout::Aligned,
+            ..CsvOptions::default()
+        };
+        assert_eq!(
+            write(&sample(), &options),
+            [
+                "item ,price,note              ",
+                "Tea  ,  2.5,\"green, \"\"loose\"\"\"",
+                "Water,  15%",
+                "     ,12.25,TRUE              ",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn reads_written_cells_back() {
+        for layout in [CsvLayout::Canonical, CsvLayout::Aligned] {
+            let options = CsvOptions {
+                layout,
+                ..CsvOptions::default()
+            };
+            let cells: Vec<Vec<String>> =
+                texts(read(&write(&sample(), &options), &options).unwrap())
+                    .iter()
+                    .map(|row| row.iter().map(|cell| cell.trim().to_string()).collect())
+                    .collect();
+            assert_eq!(
+                cells,
+                strings(&[
+                    &["item", "price", "note"],
+                    &["Tea", "2.5", "green, \"loose\""],
+                    &["Water", "15%"],
+                    &["", "12.25", "TRUE"]
+                ])
+            );
+        }
+    }
+
+    #[test]
+    fn quotes_text_that_would_read_back_as_other_cells() {
+        let texts = [
+            "=A1",
+            " =A1",
+            "15%",
+            " x ",
+            "15",
+            "TRUE",
+            "2015-05-20",
+            "'quoted",
+            "'a, b",
+            "",
+            "^",
+            "=OOPS(1)",
+            "plain",
+        ];
+        assert_eq!(
+            write(
+                &[vec![
+                    Cell::Text("=A1".to_string()),
+                    Cell::Text("15%".to_string())
+                ]],
+                &CsvOptions::default()
+            ),
+            "\"'=A1\",\"15%\""
+        );
+        for layout in [CsvLayout::Canonical, CsvLayout::Aligned] {
+            let options = CsvOptions {
+                layout,
+                ..CsvOptions::default()
+            };
+            let mut cells: Vec<Vec<Cell>> = texts
+                .iter()
+                .map(|text| vec![Cell::Text(text.to_string())])
+                .collect();
+            cells.extend(sample());
+            let registry = FunctionRegistry::default();
+            let sheet = Sheet::parse_csv(&write(&cells, &options), &options, &registry).unwrap();
+            assert_eq!(sheet.cells, cells);
+        }
+    }
+}
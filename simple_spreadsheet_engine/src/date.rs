@@ -0,0 +1,230 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::cell_error::CellError;
+
+// Dates are serial numbers like in spreadsheets: the whole part counts the days since 1899-12-30 and
+// the fraction is the time of day. This matches the serial numbers of common spreadsheets for all
+// dates from 1900-03-01 on.
+
+/// Serial number of 1970-01-01.
+const UNIX_EPOCH_SERIAL: i64 = 25569;
+
+/// Serial number of 9999-12-31, the last date that spreadsheets support.
+pub const MAX_SERIAL: f64 = 2958465.0;
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_from_march = (month as i64 + 9) % 12;
+    let day_of_year = (153 * month_from_march + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Date of a number of days since 1970-01-01, the inverse of `days_from_civil`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Serial number of a date. Months and days outside of their range carry over like in spreadsheets,
+/// so month 13 is January of the next year and day 0 is the last day of the previous month.
+pub fn serial(year: i64, month: i64, day: i64) -> f64 {
+    let year = year + (month - 1).div_euclid(12);
+    let month = (month - 1).rem_euclid(12) as u32 + 1;
+    (days_from_civil(year, month, 1) + day - 1 + UNIX_EPOCH_SERIAL) as f64
+}
+
+/// Like `serial`, but None if the date lies before 1899-12-30 or after 9999-12-31.
+pub fn checked_serial(year: i64, month: i64, day: i64) -> Option<f64> {
+    let year = year.checked_add(month.checked_sub(1)?.div_euclid(12))?;
+    // Years this far out of range would overflow `days_from_civil`.
+    if year.abs() > 100_000 {
+        return None;
+    }
+    let month = (month - 1).rem_euclid(12) as u32 + 1;
+    let serial = (days_from_civil(year, month, 1) + UNIX_EPOCH_SERIAL) as f64 + day as f64 - 1.0;
+    check(serial).ok()
+}
+
+/// The serial number if it lies between 1899-12-30 and the end of 9999-12-31, else a #NUM! error.
+/// Serial numbers passed to `ymd` must be checked first.
+pub fn check(serial: f64) -> Result<f64, CellError> {
+    if (0.0..MAX_SERIAL + 1.0).contains(&serial) {
+        Ok(serial)
+    } else {
+        Err(out_of_range())
+    }
+}
+
+pub fn out_of_range() -> CellError {
+    CellError::num("Dates must lie between 1899-12-30 and 9999-12-31.".to_string())
+}
+
+/// Year, month and day of a serial number.
+pub fn ymd(serial: f64) -> (i64, u32, u32) {
+    civil_from_days(serial.floor() as i64 - UNIX_EPOCH_SERIAL)
+}
+
+/// Serial number of the current date (UTC).
+pub fn today() -> f64 {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    (seconds / 86400) as f64 + UNIX_EPOCH_SERIAL as f64
+}
+
+/// Parse an ISO 8601 date like 2015-05-20, optionally with a time like 2015-05-20T10:30 or
+/// 2015-05-20 10:30:15.
+pub fn parse(input: &str) -> Option<f64> {
+    let (date, time) = match input.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time.trim_start())),
+        None => (input, None),
+    };
+
+    let parts: Vec<&str> = date.split('-').collect();
+    let (year, month, day) = match parts[..] {
+        [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2 => (
+            y.parse::<i64>().ok()?,
+            m.parse::<u32>().ok()?,
+            d.parse::<u32>().ok()?,
+        ),
+        _ => return None,
+    };
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let seconds = match time {
+        Some(time) => {
+            let parts = time
+                .split(':')
+                .map(|part| match part.len() {
+                    2 => part.parse::<u32>().ok(),
+                    _ => None,
+                })
+                .collect::<Option<Vec<u32>>>()?;
+            match parts[..] {
+                [h, m] if h < 24 && m < 60 => h * 3600 + m * 60,
+                [h, m, s] if h < 24 && m < 60 && s < 60 => h * 3600 + m * 60 + s,
+                _ => return None,
+            }
+        }
+        None => 0,
+    };
+
+    Some(serial(year, month as i64, day as i64) + seconds as f64 / 86400.0)
+}
+
+/// Format a serial number as an ISO 8601 date, with the time if it is not midnight.
+pub fn format(serial: f64) -> String {
+    let (year, month, day) = ymd(serial);
+    let seconds = ((serial - serial.floor()) * 86400.0).round() as u32;
+    match seconds {
+        0 => format!("{:04}-{:02}-{:02}", year, month, day),
+        // Rounding up to the next day would need a different date.
+        86400 => format(serial.floor() + 1.0),
+        _ => format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            year,
+            month,
+            day,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cell_error::CellError;
+    use crate::date::{check, checked_serial, format, parse, serial, ymd, MAX_SERIAL};
+
+    #[test]
+    fn matches_spreadsheet_serial_numbers() {
+        assert_eq!(serial(1900, 3, 1), 61.0);
+        assert_eq!(serial(1970, 1, 1), 25569.0);
+        assert_eq!(serial(2015, 5, 20), 42144.0);
+        assert_eq!(ymd(42144.0), (2015, 5, 20));
+        assert_eq!(ymd(61.0), (1900, 3, 1));
+    }
+
+    #[test]
+    fn carries_over_months_and_days() {
+        assert_eq!(ymd(serial(2015, 14, 1)), (2016, 2, 1));
+        assert_eq!(ymd(serial(2016, 3, 0)), (2016, 2, 29));
+        assert_eq!(ymd(serial(2016, 0, 1)), (2015, 12, 1));
+        assert_eq!(ymd(serial(2015, 1, 32)), (2015, 2, 1));
+    }
+
+    #[test]
+    fn checks_the_range_of_dates() {
+        assert_eq!(checked_serial(1899, 12, 30), Some(0.0));
+        assert_eq!(checked_serial(9999, 12, 31), Some(MAX_SERIAL));
+        assert_eq!(checked_serial(10000, 0, 31), Some(MAX_SERIAL));
+        assert_eq!(checked_serial(1899, 12, 29), None);
+        assert_eq!(checked_serial(10000, 1, 1), None);
+        assert_eq!(checked_serial(1_000_000_000_000_000_000, 1, 1), None);
+        assert_eq!(checked_serial(2015, i64::MAX, 1), None);
+        assert_eq!(checked_serial(2015, 1, i64::MAX), None);
+        assert_eq!(check(MAX_SERIAL + 0.5), Ok(MAX_SERIAL + 0.5));
+        assert_eq!(
+            check(-1.0),
+            Err(CellError::num(
+                "Dates must lie between 1899-12-30 and 9999-12-31.".to_string()
+            ))
+        );
+        assert!(check(1e300).is_err());
+        assert!(check(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn parses_iso_dates() {
+        assert_eq!(parse("2015-05-20"), Some(42144.0));
+        assert_eq!(parse("2015-05-20T12:00"), Some(42144.5));
+        assert_eq!(parse("2015-05-20 18:00:00"), Some(42144.75));
+        assert_eq!(parse("2015-02-29"), None);
+        assert_eq!(parse("2015-13-01"), None);
+        assert_eq!(parse("2015-5-20"), None);
+        assert_eq!(parse("2015-05-20T25:00"), None);
+        assert_eq!(parse("20-05-2015"), None);
+        assert_eq!(parse("total"), None);
+    }
+
+    #[test]
+    fn formats_iso_dates() {
+        assert_eq!(format(42144.0), "2015-05-20");
+        assert_eq!(format(42144.75), "2015-05-20 18:00:00");
+        assert_eq!(format(42144.0 + 3661.0 / 86400.0), "2015-05-20 01:01:01");
+        assert_eq!(format(42144.9999999), "2015-05-21");
+    }
+}
@@ -0,0 +1,256 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use crate::cell::Cell;
+use crate::sheet::Sheet;
+
+/// Zero-based (row, col) index of a cell in `Sheet::cells`.
+pub type Index = (usize, usize);
+
+/// Directed graph of the references between the cells of a sheet. Every expression cell has an edge
+/// to each cell its expression reads. Cells are identified by their zero-based (row, col) index.
+#[derive(Debug)]
+pub struct DependencyGraph {
+    dependencies: BTreeMap<Index, Vec<Index>>,
+}
+
+impl DependencyGraph {
+    /// Collect the references of all expression cells in the sheet. Ranges are clamped to the
+    /// sheet, as cells beyond it are empty and never need to be resolved first.
+    pub fn build(sheet: &Sheet) -> Self {
+        let size = (sheet.cells.len(), sheet.width());
+        let mut dependencies = BTreeMap::new();
+        for (i, row) in sheet.cells.iter().enumerate() {
+            for (j, cell) in row.iter().enumerate() {
+                if let Cell::Expression(e) = cell {
+                    let mut references = e.references(i, j, size);
+                    references.sort();
+                    references.dedup();
+                    dependencies.insert((i, j), references);
+                }
+            }
+        }
+
+        DependencyGraph { dependencies }
+    }
+
+    /// The cells that the expression cell at (row, col) reads.
+    pub fn dependencies(&self, row: usize, col: usize) -> &[Index] {
+        self.dependencies
+            .get(&(row, col))
+            .map_or(&[], |references| references.as_slice())
+    }
+
+    /// Split the expression cells into strongly connected components (Tarjan's algorithm). Components
+    /// are returned in evaluation order: every component comes after the components it reads from.
+    /// A component with more than one cell, or a single cell that reads itself, is a circular reference.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<Index>> {
+        let mut visit_index: BTreeMap<Index, usize> = BTreeMap::new();
+        let mut lowlink: BTreeMap<Index, usize> = BTreeMap::new();
+        let mut stack: Vec<Index> = Vec::new();
+        let mut on_stack: BTreeSet<Index> = BTreeSet::new();
+        let mut components = Vec::new();
+
+        for root in self.dependencies.keys() {
+            if visit_index.contains_key(root) {
+                continue;
+            }
+
+            // Iterative depth-first search to not overflow the call stack on long reference chains.
+            // Every frame holds a cell and the position of the next dependency to visit.
+            let mut call_stack: Vec<(Index, usize)> = vec![(*root, 0)];
+            while let Some(&(cell, next)) = call_stack.last() {
+                if next == 0 {
+                    let count = visit_index.len();
+                    visit_index.insert(cell, count);
+                    lowlink.insert(cell, count);
+                    stack.push(cell);
+                    on_stack.insert(cell);
+                }
+
+                if let Some(&dependency) = self.dependencies(cell.0, cell.1).get(next) {
+                    call_stack.last_mut().unwrap().1 += 1;
+                    if !self.dependencies.contains_key(&dependency) {
+                        // Literal cells are already resolved and can't be part of a cycle.
+                        continue;
+                    }
+                    match visit_index.get(&dependency) {
+                        None => call_stack.push((dependency, 0)),
+                        Some(&i) if on_stack.contains(&dependency) => {
+                            let low = lowlink.get_mut(&cell).unwrap();
+                            *low = (*low).min(i);
+                        }
+                        Some(_) => (),
+                    }
+                    continue;
+                }
+
+                call_stack.pop();
+                let cell_lowlink = lowlink[&cell];
+                if let Some(&(parent, _)) = call_stack.last() {
+                    let low = lowlink.get_mut(&parent).unwrap();
+                    *low = (*low).min(cell_lowlink);
+                }
+
+                if cell_lowlink == visit_index[&cell] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack.remove(&member);
+                        component.push(member);
+                        if member == cell {
+                            break;
+                        }
+                    }
+                    component.sort();
+                    components.push(component);
+                }
+            }
+        }
+
+        components
+    }
+
+    /// Whether the strongly connected component is a circular reference.
+    pub fn is_circular(&self, component: &[Index]) -> bool {
+        match component {
+            [cell] => self.dependencies(cell.0, cell.1).contains(cell),
+            _ => component.len() > 1,
+        }
+    }
+
+    /// The shortest path of references that leads from `start` back to itself, staying within the
+    /// given circular component. The path starts and ends with `start`.
+    pub fn cycle_path(&self, start: Index, component: &[Index]) -> Vec<Index> {
+        let mut previous: BTreeMap<Index, Index> = BTreeMap::new();
+        let mut queue: VecDeque<Index> = VecDeque::from([start]);
+
+        while let Some(cell) = queue.pop_front() {
+            for &dependency in self.dependencies(cell.0, cell.1) {
+                if dependency == start {
+                    let mut path = vec![start, cell];
+                    let mut current = cell;
+                    while current != start {
+                        current = previous[&current];
+                        path.push(current);
+                    }
+                    path.reverse();
+                    return path;
+                }
+                if component.contains(&dependency) && !previous.contains_key(&dependency) {
+                    previous.insert(dependency, cell);
+                    queue.push_back(dependency);
+                }
+            }
+        }
+
+        vec![start]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dependency_graph::DependencyGraph;
+    use crate::function_registry::FunctionRegistry;
+    use crate::sheet::Sheet;
+
+    fn parse_input(input: String) -> Sheet {
+        Sheet::parse_input(input, &FunctionRegistry::default()).unwrap()
+    }
+
+    #[test]
+    fn collects_references_of_expressions() {
+        let sheet = parse_input("1, =A1 + 1, =SUM(A1:B1)\n=C1 * B1".to_string());
+        let graph = DependencyGraph::build(&sheet);
+
+        assert_eq!(graph.dependencies(0, 0), &[]);
+        assert_eq!(graph.dependencies(0, 1), &[(0, 0)]);
+        assert_eq!(graph.dependencies(0, 2), &[(0, 0), (0, 1)]);
+        assert_eq!(graph.dependencies(1, 0), &[(0, 1), (0, 2)]);
+    }
+
+    #[test]
+    fn orders_components_after_their_dependencies() {
+        let sheet = parse_input("=B1 * 2, =C1 + 1, =A2 - 1\n10".to_string());
+        let graph = DependencyGraph::build(&sheet);
+
+        assert_eq!(
+            graph.strongly_connected_components(),
+            vec![vec![(0, 2)], vec![(0, 1)], vec![(0, 0)]]
+        );
+    }
+
+    #[test]
+    fn keeps_row_major_order_for_independent_cells() {
+        let sheet = parse_input("=1 + 1, =2 + 2\n=3 + 3".to_string());
+        let graph = DependencyGraph::build(&sheet);
+
+        assert_eq!(
+            graph.strongly_connected_components(),
+            vec![vec![(0, 0)], vec![(0, 1)], vec![(1, 0)]]
+        );
+    }
+
+    #[test]
+    fn detects_circular_references() {
+        let sheet = parse_input("=B1 + 1, =A1 + 1, =A1 * 2, 5, =D1 - 1, =F1 + 1".to_string());
+        let graph = DependencyGraph::build(&sheet);
+        let components = graph.strongly_connected_components();
+
+        assert_eq!(
+            components,
+            vec![
+                vec![(0, 0), (0, 1)],
+                vec![(0, 2)],
+                vec![(0, 4)],
+                vec![(0, 5)]
+            ]
+        );
+        assert_eq!(
+            components
+                .iter()
+                .map(|component| graph.is_circular(component))
+                .collect::<Vec<bool>>(),
+            vec![true, false, false, true]
+        );
+    }
+
+    #[test]
+    fn detects_circular_references_through_ranges() {
+        let sheet = parse_input("1, 2, =SUM(A1:C1)\n=SUM(A3:B3)\n3, =A2 / 2".to_string());
+        let graph = DependencyGraph::build(&sheet);
+
+        assert!(graph.is_circular(&[(0, 2)]));
+        assert!(graph.is_circular(&[(1, 0), (2, 1)]));
+    }
+
+    #[test]
+    fn clamps_ranges_to_the_sheet() {
+        let sheet = parse_input(
+            "1, =SUM(A1:A20000000)
+=SUM(B2:ZZ9), =SUM(A1:C5)"
+                .to_string(),
+        );
+        let graph = DependencyGraph::build(&sheet);
+
+        assert_eq!(graph.dependencies(0, 1), &[(0, 0), (1, 0)]);
+        assert_eq!(graph.dependencies(1, 0), &[(1, 1)]);
+        assert_eq!(graph.dependencies(1, 1), &[(0, 0), (0, 1), (1, 0), (1, 1)]);
+        assert!(graph.is_circular(&[(1, 1)]));
+    }
+
+    #[test]
+    fn finds_shortest_cycle_path() {
+        let sheet = parse_input("=B1 + C1, =C1 + 1, =A1 * 2\n=A2 - 1".to_string());
+        let graph = DependencyGraph::build(&sheet);
+        let component = [(0, 0), (0, 1), (0, 2)];
+
+        assert_eq!(
+            graph.cycle_path((0, 0), &component),
+            vec![(0, 0), (0, 2), (0, 0)]
+        );
+        assert_eq!(
+            graph.cycle_path((0, 1), &component),
+            vec![(0, 1), (0, 2), (0, 0), (0, 1)]
+        );
+        assert_eq!(graph.cycle_path((1, 0), &[(1, 0)]), vec![(1, 0), (1, 0)]);
+    }
+}
@@ -1,9 +1,15 @@
-use crate::cell::Cell;
+use std::fmt;
+
+use crate::cell_error::CellError;
+use crate::cell_pos::CellPos;
+use crate::dependency_graph::Index;
 use crate::formula::Formula;
-use crate::function::Function;
+use crate::function_registry::FunctionRegistry;
+use crate::parser::ParseError;
 use crate::sheet::Sheet;
+use crate::value::Value;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Clone {
     Left,
     Right,
@@ -11,46 +17,64 @@ pub enum Clone {
 }
 
 impl Clone {
-    fn resolve(&self, row: usize, col: usize, sheet: &Sheet, resolved: &Sheet) {
-        let (row, col) = match self {
-            Clone::Left => (row, col - 1),
-            Clone::Right => (row, col + 1),
-            Clone::Top => (row - 1, col),
-        };
-        let target = &sheet.cells[row][col];
+    /// Zero-based (row, col) index of the neighbouring cell that is cloned, None if the cell at
+    /// (row, col) is on the edge of the sheet, e.g. `^` in the first row.
+    pub(crate) fn target(&self, row: usize, col: usize) -> Option<Index> {
+        match self {
+            Clone::Left => Some((row, col.checked_sub(1)?)),
+            Clone::Right => Some((row, col + 1)),
+            Clone::Top => Some((row.checked_sub(1)?, col)),
+        }
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Expression {
-    Clone(usize, usize, Clone),
-    Function(Function),
+    Clone(Clone),
     Formula(Formula),
 }
 
 impl Expression {
-    pub fn parse(row: usize, col: usize, input: &str) -> Result<Expression, &'static str> {
-        if input.len() == 1 {
-            match input.chars().next().unwrap() {
-                '^' => Ok(Expression::Clone(row, col, Clone::Top)),
-                '<' => Ok(Expression::Clone(row, col, Clone::Left)),
-                '>' => Ok(Expression::Clone(row, col, Clone::Right)),
-                _ => Err("Unsupported expression."),
-            }
-        } else if let Ok(fun) = Function::parse(input) {
-            Ok(Expression::Function(fun))
-        } else if let Ok(formula) = Formula::parse(input) {
-            Ok(Expression::Formula(formula))
-        } else {
-            Err("Unsupported expression.")
+    pub fn parse(input: &str, registry: &FunctionRegistry) -> Result<Expression, ParseError> {
+        match input {
+            "^" => Ok(Expression::Clone(Clone::Top)),
+            "<" => Ok(Expression::Clone(Clone::Left)),
+            ">" => Ok(Expression::Clone(Clone::Right)),
+            _ => Formula::parse(input, registry).map(Expression::Formula),
+        }
+    }
+
+    /// Zero-based (row, col) indices of the cells that must be resolved before this expression,
+    /// which lives at (row, col), can be resolved. Ranges only include the cells of a sheet of
+    /// (rows, columns) size.
+    pub fn references(&self, row: usize, col: usize, size: (usize, usize)) -> Vec<Index> {
+        match self {
+            Expression::Clone(e) => e.target(row, col).into_iter().collect(),
+            Expression::Formula(e) => e.references(size),
         }
     }
 
-    pub fn resolve(&self, sheet: &Sheet, resolved: &mut Sheet) {
+    /// Resolve the expression at (row, col). Referenced cells are read from `resolved`. Clones have
+    /// no value of their own, `Sheet::resolve` replaces them with the cell they copy first.
+    pub fn resolve(&self, row: usize, col: usize, resolved: &Sheet) -> Result<Value, CellError> {
         match self {
-            Expression::Clone(row, col, e) => e.resolve(*row, *col, sheet, resolved),
-            Expression::Function(e) => e.resolve(sheet, resolved),
-            Expression::Formula(e) => e.resolve(sheet, resolved),
+            Expression::Clone(_) => Err(CellError::reference(format!(
+                "The clone in cell {} was not expanded.",
+                CellPos::from_index(row, col).str
+            ))),
+            Expression::Formula(e) => e.resolve(resolved),
+        }
+    }
+}
+
+impl fmt::Display for Expression {
+    /// The expression as it is written in a cell.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expression::Clone(Clone::Left) => write!(f, "<"),
+            Expression::Clone(Clone::Right) => write!(f, ">"),
+            Expression::Clone(Clone::Top) => write!(f, "^"),
+            Expression::Formula(formula) => write!(f, "={}", formula),
         }
     }
 }
@@ -62,62 +86,46 @@ mod tests {
     use crate::expression::{Clone, Expression};
     use crate::formula::{ArithmeticOperator, Formula, Operator};
     use crate::function::Function;
-    use crate::number_or_cell_pos::NumberOrCellPos;
+    use crate::function_registry::FunctionRegistry;
+    use crate::parser::ParseError;
+
+    fn parse(input: &str) -> Result<Expression, ParseError> {
+        Expression::parse(input, &FunctionRegistry::default())
+    }
 
     #[test]
     fn can_parse_clone_expressions() {
-        assert_eq!(
-            Expression::parse('^').unwrap(),
-            Expression::Clone(Clone::Top)
-        );
-        assert_eq!(
-            Expression::parse('<').unwrap(),
-            Expression::Clone(Clone::Left)
-        );
-        assert_eq!(
-            Expression::parse('>').unwrap(),
-            Expression::Clone(Clone::Right)
-        );
-        assert_eq!(
-            Expression::parse("^").unwrap(),
-            Expression::Clone(Clone::Top)
-        );
-        assert_eq!(
-            Expression::parse("<").unwrap(),
-            Expression::Clone(Clone::Left)
-        );
-        assert_eq!(
-            Expression::parse(">").unwrap(),
-            Expression::Clone(Clone::Right)
-        );
+        assert_eq!(parse("^").unwrap(), Expression::Clone(Clone::Top));
+        assert_eq!(parse("<").unwrap(), Expression::Clone(Clone::Left));
+        assert_eq!(parse(">").unwrap(), Expression::Clone(Clone::Right));
     }
 
     #[test]
     fn can_parse_arithmetic_expressions() {
         assert_eq!(
-            Expression::parse("A1 + B2").unwrap(),
+            parse("A1 + B2").unwrap(),
             Expression::Formula(Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Addition),
-                NumberOrCellPos::CellPos(CellPos::new("A1".to_string(), 1, 1)),
-                NumberOrCellPos::CellPos(CellPos::new("B2".to_string(), 2, 2))
+                Formula::CellPos(CellPos::new("A1".to_string(), 1, 1)),
+                Formula::CellPos(CellPos::new("B2".to_string(), 2, 2))
             ))
         );
 
         assert_eq!(
-            Expression::parse("9.60 * 0.8").unwrap(),
+            parse("9.60 * 0.8").unwrap(),
             Expression::Formula(Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Multiplication),
-                NumberOrCellPos::Number(9.60),
-                NumberOrCellPos::Number(0.8)
+                Formula::Number(9.60),
+                Formula::Number(0.8)
             ))
         );
 
         assert_eq!(
-            Expression::parse("A1 - 1").unwrap(),
+            parse("A1 - 1").unwrap(),
             Expression::Formula(Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Subtraction),
-                NumberOrCellPos::CellPos(CellPos::new("A1".to_string(), 1, 1)),
-                NumberOrCellPos::Number(1.0)
+                Formula::CellPos(CellPos::new("A1".to_string(), 1, 1)),
+                Formula::Number(1.0)
             ))
         );
     }
@@ -125,51 +133,57 @@ mod tests {
     #[test]
     fn can_parse_function_expressions() {
         assert_eq!(
-            Expression::parse("AVG(A1:A3)").unwrap(),
-            Expression::Function(Function::Avg(CellRange::new(
-                "A1:A3".to_string(),
-                1,
-                1,
-                3,
-                1
+            parse("AVG(A1:A3)").unwrap(),
+            Expression::Formula(Formula::Function(Function::builtin(
+                "AVG",
+                vec![Formula::CellRange(CellRange::new(
+                    "A1:A3".to_string(),
+                    1,
+                    1,
+                    3,
+                    1
+                ))]
             )))
         );
 
         assert_eq!(
-            Expression::parse("COUNT(B2:B11)").unwrap(),
-            Expression::Function(Function::Count(CellRange::new(
-                "B2:B11".to_string(),
-                2,
-                2,
-                11,
-                2
+            parse("COUNT(B2:B11)").unwrap(),
+            Expression::Formula(Formula::Function(Function::builtin(
+                "COUNT",
+                vec![Formula::CellRange(CellRange::new(
+                    "B2:B11".to_string(),
+                    2,
+                    2,
+                    11,
+                    2
+                ))]
             )))
         );
 
         assert_eq!(
-            Expression::parse("SUM(D2:D4)").unwrap(),
-            Expression::Function(Function::Sum(CellRange::new(
-                "D2:D4".to_string(),
-                2,
-                4,
-                4,
-                4
+            parse("SUM(D2:D4)").unwrap(),
+            Expression::Formula(Formula::Function(Function::builtin(
+                "SUM",
+                vec![Formula::CellRange(CellRange::new(
+                    "D2:D4".to_string(),
+                    2,
+                    4,
+                    4,
+                    4
+                ))]
             )))
         );
     }
 
     #[test]
     fn handles_invalid_input() {
-        let err = Err("Unsupported expression.");
-        assert_eq!(Expression::parse(""), err);
-        assert_eq!(Expression::parse("v"), err);
-        assert_eq!(Expression::parse('v'), err);
-        assert_eq!(Expression::parse("=1.23 + 456"), err);
-        assert_eq!(Expression::parse("=SUM(D2:D4)"), err);
-        assert_eq!(Expression::parse("IF(1, 2, 3)"), err);
-        assert_eq!(Expression::parse("LOOKUP(F4, B5:B9, C5:C9)"), err);
-        assert_eq!(Expression::parse("DATE(2015, 5, 20)"), err);
-        assert_eq!(Expression::parse("AVG(?)"), err);
-        assert_eq!(Expression::parse("#ERROR#"), err);
+        let err = |message: &str, column| Err(ParseError::new(message.to_string(), column));
+        assert_eq!(parse(""), err("Unexpected end of formula.", 0));
+        assert_eq!(parse("v"), err("'v' is not a valid cell reference.", 0));
+        assert_eq!(parse("=1.23 + 456"), err("Unexpected '='.", 0));
+        assert_eq!(parse("=SUM(D2:D4)"), err("Unexpected '='.", 0));
+        assert_eq!(parse("AVG(?)"), err("Unexpected character '?'.", 4));
+        assert_eq!(parse("#ERROR#"), err("Unexpected character '#'.", 0));
+        assert_eq!(parse("^^"), err("Unexpected character '^'.", 0));
     }
 }
@@ -1,7 +1,21 @@
-use crate::{cell::Cell, number_or_cell_pos::NumberOrCellPos, sheet::Sheet};
+use std::cmp::Ordering;
+use std::fmt;
 
-// TODO: Add support for % operator. E.g '=A * 10%'
-#[derive(Debug, PartialEq, Eq)]
+use crate::{
+    cell_error::CellError,
+    cell_pos::CellPos,
+    cell_range::CellRange,
+    date,
+    dependency_graph::Index,
+    function::Function,
+    function_registry::{Argument, FunctionRegistry},
+    parser::ParseError,
+    parser::Parser,
+    sheet::Sheet,
+    value::Value,
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum ArithmeticOperator {
     Addition,       // A + B
     Division,       // A / B
@@ -10,235 +24,564 @@ pub enum ArithmeticOperator {
     Subtraction,    // A - B
 }
 
-// TODO: Add support for comparison operations
-// #[derive(Debug, PartialEq)]
-// pub enum ComparisonOperator {
-//     Equal,
-//     GreaterThan,
-//     GreaterThanOrEqual,
-//     LessThan,
-//     LessThanOrEqual,
-//     NotEqual,
-// }
-
-#[derive(Debug, Eq, PartialEq)]
+impl ArithmeticOperator {
+    /// Apply the operator. Like in spreadsheets, a division by zero or a result that is not a finite
+    /// number, e.g. -1 ** 0.5, is an error instead of infinity or NaN.
+    pub(crate) fn apply(&self, lhs: f64, rhs: f64) -> Result<f64, CellError> {
+        let out = match self {
+            ArithmeticOperator::Addition => lhs + rhs,
+            ArithmeticOperator::Division if rhs == 0.0 => {
+                return Err(CellError::division_by_zero(format!(
+                    "Cannot divide {} by zero.",
+                    lhs
+                )))
+            }
+            ArithmeticOperator::Division => lhs / rhs,
+            ArithmeticOperator::Exponentiation if lhs == 0.0 && rhs < 0.0 => {
+                return Err(CellError::division_by_zero(format!(
+                    "Cannot raise zero to the negative power {}.",
+                    rhs
+                )))
+            }
+            ArithmeticOperator::Exponentiation => f64::powf(lhs, rhs),
+            ArithmeticOperator::Multiplication => lhs * rhs,
+            ArithmeticOperator::Subtraction => lhs - rhs,
+        };
+        if !out.is_finite() {
+            return Err(CellError::num(format!(
+                "{} {} {} has no finite result.",
+                lhs,
+                Operator::ArithmeticOperator(self.clone()),
+                rhs
+            )));
+        }
+        Ok(out)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComparisonOperator {
+    Equal,              // A = B
+    GreaterThan,        // A > B
+    GreaterThanOrEqual, // A >= B
+    LessThan,           // A < B
+    LessThanOrEqual,    // A <= B
+    NotEqual,           // A <> B
+}
+
+impl ComparisonOperator {
+    pub(crate) fn apply(&self, ordering: Ordering) -> bool {
+        match self {
+            ComparisonOperator::Equal => ordering == Ordering::Equal,
+            ComparisonOperator::GreaterThan => ordering == Ordering::Greater,
+            ComparisonOperator::GreaterThanOrEqual => ordering != Ordering::Less,
+            ComparisonOperator::LessThan => ordering == Ordering::Less,
+            ComparisonOperator::LessThanOrEqual => ordering != Ordering::Greater,
+            ComparisonOperator::NotEqual => ordering != Ordering::Equal,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::enum_variant_names)]
 pub enum Operator {
     ArithmeticOperator(ArithmeticOperator),
-    // TODO: Add support for comparison operations
-    // ComparisonOperator(ComparisonOperator),
-    // TODO: Add support for concatenation operations
-    // TextConcatenationOperator,
+    ComparisonOperator(ComparisonOperator),
+    TextConcatenationOperator, // A & B
+}
+
+/// Precedence of unary + and -. Like in Python, they bind tighter than * and / but looser than **,
+/// so -2 ** 2 is -4.
+pub const UNARY_PRECEDENCE: u8 = 5;
+
+/// Precedence of the postfix %, which binds tighter than any other operator, so 2 ** 50% is 2 ** 0.5.
+pub const PERCENT_PRECEDENCE: u8 = 7;
+
+impl Operator {
+    /// Binding strength of the operator, operators with a higher precedence are evaluated first.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Operator::ArithmeticOperator(op) => match op {
+                ArithmeticOperator::Addition | ArithmeticOperator::Subtraction => 3,
+                ArithmeticOperator::Division | ArithmeticOperator::Multiplication => 4,
+                ArithmeticOperator::Exponentiation => 6,
+            },
+            Operator::ComparisonOperator(_) => 1,
+            Operator::TextConcatenationOperator => 2,
+        }
+    }
+
+    /// Whether a chain of this operator groups from the right, e.g. 2 ** 3 ** 2 is 2 ** (3 ** 2).
+    pub fn is_right_associative(&self) -> bool {
+        *self == Operator::ArithmeticOperator(ArithmeticOperator::Exponentiation)
+    }
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operator::ArithmeticOperator(op) => match op {
+                ArithmeticOperator::Addition => write!(f, "+"),
+                ArithmeticOperator::Division => write!(f, "/"),
+                ArithmeticOperator::Exponentiation => write!(f, "**"),
+                ArithmeticOperator::Multiplication => write!(f, "*"),
+                ArithmeticOperator::Subtraction => write!(f, "-"),
+            },
+            Operator::ComparisonOperator(op) => match op {
+                ComparisonOperator::Equal => write!(f, "="),
+                ComparisonOperator::GreaterThan => write!(f, ">"),
+                ComparisonOperator::GreaterThanOrEqual => write!(f, ">="),
+                ComparisonOperator::LessThan => write!(f, "<"),
+                ComparisonOperator::LessThanOrEqual => write!(f, "<="),
+                ComparisonOperator::NotEqual => write!(f, "<>"),
+            },
+            Operator::TextConcatenationOperator => write!(f, "&"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UnaryOperator {
+    Minus,   // -A
+    Percent, // A%
+    Plus,    // +A
+}
+
+impl UnaryOperator {
+    pub fn precedence(&self) -> u8 {
+        match self {
+            UnaryOperator::Minus | UnaryOperator::Plus => UNARY_PRECEDENCE,
+            UnaryOperator::Percent => PERCENT_PRECEDENCE,
+        }
+    }
+
+    fn apply(&self, n: f64) -> f64 {
+        match self {
+            UnaryOperator::Minus => -n,
+            UnaryOperator::Percent => n / 100.0,
+            UnaryOperator::Plus => n,
+        }
+    }
+}
+
+impl fmt::Display for UnaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UnaryOperator::Minus => write!(f, "-"),
+            UnaryOperator::Percent => write!(f, "%"),
+            UnaryOperator::Plus => write!(f, "+"),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
-pub struct Formula {
-    operator: Operator,
-    left: NumberOrCellPos,
-    right: NumberOrCellPos,
+/// Abstract syntax tree of a formula.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Formula {
+    Binary(Operator, Box<Formula>, Box<Formula>),
+    Boolean(bool),
+    CellPos(CellPos),
+    /// A range of cells, only allowed as argument of a function.
+    CellRange(CellRange),
+    Function(Function),
+    Number(f64),
+    Text(String),
+    Unary(UnaryOperator, Box<Formula>),
 }
 
 impl Formula {
-    pub fn parse(input: &str) -> Result<Self, String> {
-        let ops = vec![
-            ("**", ArithmeticOperator::Exponentiation),
-            ("*", ArithmeticOperator::Multiplication),
-            ("/", ArithmeticOperator::Division),
-            ("+", ArithmeticOperator::Addition),
-            ("-", ArithmeticOperator::Subtraction),
-        ];
-
-        for op in ops {
-            let formula = match input.split(op.0).collect::<Vec<&str>>()[..] {
-                [lhs, rhs] => match (
-                    NumberOrCellPos::parse(lhs.trim()),
-                    NumberOrCellPos::parse(rhs.trim()),
-                ) {
-                    (Ok(left), Ok(right)) => Some(Formula {
-                        operator: Operator::ArithmeticOperator(op.1),
-                        left,
-                        right,
-                    }),
-                    _ => None,
-                },
-                _ => None,
-            };
-
-            if let Some(f) = formula {
-                return Ok(f);
+    pub fn parse(input: &str, registry: &FunctionRegistry) -> Result<Self, ParseError> {
+        Parser::parse(input, registry)
+    }
+
+    /// Create a binary operation.
+    pub fn new(operator: Operator, left: Formula, right: Formula) -> Self {
+        Formula::Binary(operator, Box::new(left), Box::new(right))
+    }
+
+    /// Binding strength of the outermost operation, used to decide where parentheses are needed.
+    fn precedence(&self) -> u8 {
+        match self {
+            Formula::Binary(op, _, _) => op.precedence(),
+            Formula::Unary(op, _) => op.precedence(),
+            _ => u8::MAX,
+        }
+    }
+
+    /// Zero-based (row, col) indices of the cells this formula reads. Ranges only include the cells
+    /// of a sheet of (rows, columns) size, see `CellRange::positions`.
+    pub fn references(&self, size: (usize, usize)) -> Vec<Index> {
+        match self {
+            Formula::Binary(_, left, right) => {
+                let mut references = left.references(size);
+                references.extend(right.references(size));
+                references
             }
+            Formula::Boolean(_) | Formula::Number(_) | Formula::Text(_) => vec![],
+            Formula::CellPos(pos) => vec![pos.index()],
+            Formula::CellRange(range) => range.positions(size).collect(),
+            Formula::Function(f) => f.references(size),
+            Formula::Unary(_, operand) => operand.references(size),
         }
+    }
 
-        // TODO: propagate the errors of incorrect operands
-        Err("Unsupported formula or incorrect operands.".to_string())
+    /// The same formula with all references moved by a number of rows and columns, like a formula
+    /// that is copied to another cell. A reference that moves above or left of A1 is an error.
+    pub fn shift(&self, rows: isize, cols: isize) -> Result<Formula, CellError> {
+        let outside = |reference: &str| {
+            CellError::reference(format!(
+                "Reference {} moves outside the sheet when shifted by {} rows and {} columns.",
+                reference, rows, cols
+            ))
+        };
+        Ok(match self {
+            Formula::Binary(op, left, right) => Formula::new(
+                op.clone(),
+                left.shift(rows, cols)?,
+                right.shift(rows, cols)?,
+            ),
+            Formula::CellPos(pos) => {
+                Formula::CellPos(pos.shift(rows, cols).ok_or_else(|| outside(&pos.str))?)
+            }
+            Formula::CellRange(range) => {
+                Formula::CellRange(range.shift(rows, cols).ok_or_else(|| outside(&range.str))?)
+            }
+            Formula::Function(f) => Formula::Function(f.shift(rows, cols)?),
+            Formula::Unary(op, operand) => {
+                Formula::Unary(op.clone(), Box::new(operand.shift(rows, cols)?))
+            }
+            Formula::Boolean(_) | Formula::Number(_) | Formula::Text(_) => self.clone(),
+        })
     }
 
-    #[allow(dead_code)]
-    pub fn new(operator: Operator, left: NumberOrCellPos, right: NumberOrCellPos) -> Self {
-        Formula {
-            operator,
-            left,
-            right,
+    /// Resolve the formula, recording its evaluation in the trace of the sheet. Literals aren't
+    /// recorded, since their value is already in the formula.
+    pub fn resolve(&self, resolved: &Sheet) -> Result<Value, CellError> {
+        if matches!(
+            self,
+            Formula::Boolean(_) | Formula::Number(_) | Formula::Text(_)
+        ) {
+            return self.evaluate(resolved);
         }
+        resolved.trace.enter();
+        let result = self.evaluate(resolved);
+        resolved.trace.exit(|| self.to_string(), &result);
+        result
     }
 
-    pub fn resolve(&self, sheet: &Sheet, resolved: &mut Sheet) {
-        if let (Some(lhs), Some(rhs)) = (self.left.resolve(sheet), self.right.resolve(sheet)) {
-            match &self.operator {
-                Operator::ArithmeticOperator(op) => match op {
-                    ArithmeticOperator::Addition => {
-                        println!("..> ={} + {}", self.left, self.right);
-                        println!("... {} + {}", lhs, rhs);
-                        let out = lhs + rhs;
-                        println!("... {}\n", out);
-                        Cell::Number(out);
-                    }
-                    ArithmeticOperator::Division => {
-                        println!("..> ={} / {}", self.left, self.right);
-                        println!("... {} / {}", lhs, rhs);
-                        let out = lhs / rhs;
-                        println!("... {}\n", out);
-                        Cell::Number(out);
-                    }
-                    ArithmeticOperator::Exponentiation => {
-                        println!("..> ={} ** {}", self.left, self.right);
-                        println!("... {} ** {}", lhs, rhs);
-                        let out = f64::powf(lhs, rhs);
-                        println!("... {}\n", out);
-                        Cell::Number(out);
-                    }
-                    ArithmeticOperator::Multiplication => {
-                        println!("..> ={} * {}", self.left, self.right);
-                        println!("... {} * {}", lhs, rhs);
-                        let out = lhs * rhs;
-                        println!("... {}\n", out);
-                        Cell::Number(out);
-                    }
-                    ArithmeticOperator::Subtraction => {
-                        println!("..> ={} - {}", self.left, self.right);
-                        println!("... {} - {}", lhs, rhs);
-                        let out = lhs - rhs;
-                        println!("... {}\n", out);
-                        Cell::Number(out);
-                    }
-                },
+    fn evaluate(&self, resolved: &Sheet) -> Result<Value, CellError> {
+        match self {
+            Formula::Binary(Operator::ArithmeticOperator(op), left, right) => {
+                let lhs = left.resolve(resolved)?;
+                let rhs = right.resolve(resolved)?;
+                resolved.trace.operands(|| operands(&lhs, &rhs));
+                // Adding days to or subtracting days from a date gives a date.
+                let is_date = |value: &Value| matches!(value, Value::Date(_));
+                let is_date_result = match op {
+                    ArithmeticOperator::Addition => is_date(&lhs) != is_date(&rhs),
+                    ArithmeticOperator::Subtraction => is_date(&lhs) && !is_date(&rhs),
+                    _ => false,
+                };
+                let lhs = left.number(lhs)?;
+                let rhs = right.number(rhs)?;
+                let out = op.apply(lhs, rhs)?;
+                Ok(if is_date_result {
+                    Value::Date(date::check(out)?)
+                } else {
+                    Value::Number(out)
+                })
+            }
+            Formula::Binary(Operator::ComparisonOperator(op), left, right) => {
+                let lhs = left.resolve(resolved)?;
+                let rhs = right.resolve(resolved)?;
+                resolved.trace.operands(|| operands(&lhs, &rhs));
+                Ok(Value::Boolean(op.apply(lhs.compare(&rhs))))
+            }
+            Formula::Binary(Operator::TextConcatenationOperator, left, right) => {
+                let lhs = left.resolve(resolved)?;
+                let rhs = right.resolve(resolved)?;
+                resolved.trace.operands(|| operands(&lhs, &rhs));
+                Ok(Value::Text(format!("{}{}", lhs, rhs)))
             }
+            Formula::Boolean(b) => Ok(Value::Boolean(*b)),
+            Formula::CellPos(pos) => {
+                let (row, col) = pos.index();
+                resolved.value(row, col)
+            }
+            Formula::CellRange(range) => Err(CellError::value(format!(
+                "Range {} can only be used as argument of a function.",
+                range.str
+            ))),
+            Formula::Function(f) => f.resolve(resolved),
+            Formula::Number(n) => Ok(Value::Number(*n)),
+            Formula::Text(t) => Ok(Value::Text(t.clone())),
+            Formula::Unary(op, operand) => {
+                let n = operand.resolve_number(resolved)?;
+                resolved
+                    .trace
+                    .operands(|| vec![Argument::Value(Value::Number(n))]);
+                Ok(Value::Number(op.apply(n)))
+            }
+        }
+    }
+
+    /// Resolve the formula to a number.
+    fn resolve_number(&self, resolved: &Sheet) -> Result<f64, CellError> {
+        self.number(self.resolve(resolved)?)
+    }
+
+    /// Convert the value of this formula to a number. An empty cell counts as 0, TRUE as 1 and FALSE
+    /// as 0, a date as its serial number and text is an error.
+    fn number(&self, value: Value) -> Result<f64, CellError> {
+        match value {
+            Value::Boolean(b) => Ok(if b { 1.0 } else { 0.0 }),
+            Value::Date(n) | Value::Number(n) => Ok(n),
+            Value::Empty => Ok(0.0),
+            Value::Text(t) => Err(CellError::value(match self {
+                Formula::CellPos(pos) => {
+                    format!(
+                        "Cell {} contains text '{}' instead of a number.",
+                        pos.str, t
+                    )
+                }
+                _ => format!("'{}' is not a number.", t),
+            })),
+        }
+    }
+
+    /// Write an operand of `parent`, adding parentheses if it would otherwise group differently.
+    fn fmt_operand(
+        &self,
+        f: &mut fmt::Formatter,
+        parent: u8,
+        needs_parentheses_on_tie: bool,
+    ) -> fmt::Result {
+        let precedence = self.precedence();
+        if precedence < parent || (precedence == parent && needs_parentheses_on_tie) {
+            write!(f, "({})", self)
         } else {
-            Cell::Number(f64::NAN);
+            write!(f, "{}", self)
+        }
+    }
+}
+
+/// The values of the operands of a binary operation, for the trace.
+fn operands(lhs: &Value, rhs: &Value) -> Vec<Argument> {
+    vec![Argument::Value(lhs.clone()), Argument::Value(rhs.clone())]
+}
+
+impl fmt::Display for Formula {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Formula::Binary(op, left, right) => {
+                let right_associative = op.is_right_associative();
+                left.fmt_operand(f, op.precedence(), right_associative)?;
+                write!(f, " {} ", op)?;
+                right.fmt_operand(f, op.precedence(), !right_associative)
+            }
+            Formula::Boolean(b) => write!(f, "{}", Value::Boolean(*b)),
+            Formula::CellPos(pos) => write!(f, "{}", pos.str),
+            Formula::CellRange(range) => write!(f, "{}", range.str),
+            Formula::Function(fun) => write!(f, "{}", fun),
+            Formula::Number(n) => write!(f, "{}", n),
+            Formula::Text(t) => write!(f, "\"{}\"", t.replace('"', "\"\"")),
+            Formula::Unary(UnaryOperator::Percent, operand) => {
+                operand.fmt_operand(f, PERCENT_PRECEDENCE, false)?;
+                write!(f, "%")
+            }
+            Formula::Unary(op, operand) => {
+                write!(f, "{}", op)?;
+                operand.fmt_operand(f, op.precedence(), false)
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::cell_error::CellError;
     use crate::cell_pos::CellPos;
     use crate::formula::{ArithmeticOperator, Formula, Operator};
-    use crate::number_or_cell_pos::NumberOrCellPos;
+    use crate::function_registry::FunctionRegistry;
+    use crate::parser::ParseError;
+
+    fn parse(input: &str) -> Result<Formula, ParseError> {
+        Formula::parse(input, &FunctionRegistry::default())
+    }
 
     #[test]
+    fn reports_arithmetic_errors() {
+        assert_eq!(ArithmeticOperator::Division.apply(1.0, 4.0), Ok(0.25));
+        assert_eq!(
+            ArithmeticOperator::Division.apply(1.0, 0.0),
+            Err(CellError::division_by_zero(
+                "Cannot divide 1 by zero.".to_string()
+            ))
+        );
+        assert_eq!(
+            ArithmeticOperator::Exponentiation.apply(0.0, -1.0),
+            Err(CellError::division_by_zero(
+                "Cannot raise zero to the negative power -1.".to_string()
+            ))
+        );
+        assert_eq!(
+            ArithmeticOperator::Exponentiation.apply(-1.0, 0.5),
+            Err(CellError::num(
+                "-1 ** 0.5 has no finite result.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
     fn can_parse_arithmetic() {
         assert_eq!(
-            Formula::parse("1.23 + 456").unwrap(),
+            parse("1.23 + 456").unwrap(),
             Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Addition),
-                NumberOrCellPos::Number(1.23),
-                NumberOrCellPos::Number(456.0)
+                Formula::Number(1.23),
+                Formula::Number(456.0)
             )
         );
 
         assert_eq!(
-            Formula::parse("A1 + B2").unwrap(),
+            parse("A1 + B2").unwrap(),
             Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Addition),
-                NumberOrCellPos::CellPos(CellPos::new("A1".to_string(), 1, 1)),
-                NumberOrCellPos::CellPos(CellPos::new("B2".to_string(), 2, 2))
+                Formula::CellPos(CellPos::new("A1".to_string(), 1, 1)),
+                Formula::CellPos(CellPos::new("B2".to_string(), 2, 2))
             )
         );
 
         assert_eq!(
-            Formula::parse("C3 / 0 ").unwrap(),
+            parse("C3 / 0 ").unwrap(),
             Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Division),
-                NumberOrCellPos::CellPos(CellPos::new("C3".to_string(), 3, 3)),
-                NumberOrCellPos::Number(0.0)
+                Formula::CellPos(CellPos::new("C3".to_string(), 3, 3)),
+                Formula::Number(0.0)
             )
         );
 
         assert_eq!(
-            Formula::parse("1 / 2").unwrap(),
+            parse("1 / 2").unwrap(),
             Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Division),
-                NumberOrCellPos::Number(1.0),
-                NumberOrCellPos::Number(2.0)
+                Formula::Number(1.0),
+                Formula::Number(2.0)
             )
         );
 
         assert_eq!(
-            Formula::parse("0 ** 5").unwrap(),
+            parse("0 ** 5").unwrap(),
             Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Exponentiation),
-                NumberOrCellPos::Number(0.0),
-                NumberOrCellPos::Number(5.0)
+                Formula::Number(0.0),
+                Formula::Number(5.0)
             )
         );
 
         assert_eq!(
-            Formula::parse("Z20 ** 3").unwrap(),
+            parse("Z20 ** 3").unwrap(),
             Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Exponentiation),
-                NumberOrCellPos::CellPos(CellPos::new("Z20".to_string(), 20, 26)),
-                NumberOrCellPos::Number(3.0)
+                Formula::CellPos(CellPos::new("Z20".to_string(), 20, 26)),
+                Formula::Number(3.0)
             )
         );
 
         assert_eq!(
-            Formula::parse("9.60 * 0.8").unwrap(),
+            parse("9.60 * 0.8").unwrap(),
             Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Multiplication),
-                NumberOrCellPos::Number(9.60),
-                NumberOrCellPos::Number(0.8)
+                Formula::Number(9.60),
+                Formula::Number(0.8)
             )
         );
 
         assert_eq!(
-            Formula::parse("B2 * C2").unwrap(),
+            parse("B2 * C2").unwrap(),
             Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Multiplication),
-                NumberOrCellPos::CellPos(CellPos::new("B2".to_string(), 2, 2)),
-                NumberOrCellPos::CellPos(CellPos::new("C2".to_string(), 2, 3))
+                Formula::CellPos(CellPos::new("B2".to_string(), 2, 2)),
+                Formula::CellPos(CellPos::new("C2".to_string(), 2, 3))
             )
         );
 
         assert_eq!(
-            Formula::parse("A1 - 1").unwrap(),
+            parse("A1 - 1").unwrap(),
             Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Subtraction),
-                NumberOrCellPos::CellPos(CellPos::new("A1".to_string(), 1, 1)),
-                NumberOrCellPos::Number(1.0)
+                Formula::CellPos(CellPos::new("A1".to_string(), 1, 1)),
+                Formula::Number(1.0)
             )
         );
 
         assert_eq!(
-            Formula::parse("0 - 3.141592").unwrap(),
+            parse("0 - 3.141592").unwrap(),
             Formula::new(
                 Operator::ArithmeticOperator(ArithmeticOperator::Subtraction),
-                NumberOrCellPos::Number(0.0),
-                NumberOrCellPos::Number(3.141592)
+                Formula::Number(0.0),
+                Formula::Number(3.141592)
             )
         );
     }
 
     #[test]
     fn handles_invalid_input() {
-        let err = Err("Unsupported formula or incorrect operands.".to_string());
-        assert_eq!(Formula::parse("=1.23 + 456"), err);
-        assert_eq!(Formula::parse("=B2 * C2"), err);
-        assert_eq!(Formula::parse("1.23 ++ 456"), err);
-        assert_eq!(Formula::parse("+ A1 B2"), err);
-        assert_eq!(Formula::parse("C3 0 /"), err);
-        assert_eq!(Formula::parse("? 1 2"), err);
-        assert_eq!(Formula::parse("0 * 5%"), err);
-        assert_eq!(Formula::parse("=SUM(D2:D4)"), err);
-        assert_eq!(Formula::parse("=XYZ123"), err);
-        assert_eq!(Formula::parse("=nope + 1"), err);
+        let err = |message: &str, column| Err(ParseError::new(message.to_string(), column));
+        assert_eq!(parse("=1.23 + 456"), err("Unexpected '='.", 0));
+        assert_eq!(parse("=B2 * C2"), err("Unexpected '='.", 0));
+        assert_eq!(parse("1.23 +* 456"), err("Unexpected '*'.", 6));
+        assert_eq!(parse("+ A1 B2"), err("Unexpected 'B2'.", 5));
+        assert_eq!(parse("C3 0 /"), err("Unexpected '0'.", 3));
+        assert_eq!(parse("? 1 2"), err("Unexpected character '?'.", 0));
+        assert_eq!(parse("0 * %5"), err("Unexpected '%'.", 4));
+        assert_eq!(parse("=SUM(D2:D4)"), err("Unexpected '='.", 0));
+        assert_eq!(parse("=XYZ123"), err("Unexpected '='.", 0));
+        assert_eq!(
+            parse("nope + 1"),
+            err("'nope' is not a valid cell reference.", 0)
+        );
+    }
+
+    #[test]
+    fn displays_formulas() {
+        let display = |input: &str| parse(input).unwrap().to_string();
+        assert_eq!(display("A1+B2*2"), "A1 + B2 * 2");
+        assert_eq!(display("(A1 + B2) * 2"), "(A1 + B2) * 2");
+        assert_eq!(display("8 - (4 - 2)"), "8 - (4 - 2)");
+        assert_eq!(display("(8 - 4) - 2"), "8 - 4 - 2");
+        assert_eq!(display("(2 ** 3) ** 2"), "(2 ** 3) ** 2");
+        assert_eq!(display("2 ** (3 ** 2)"), "2 ** 3 ** 2");
+        assert_eq!(display("-(1 + 2)"), "-(1 + 2)");
+        assert_eq!(display("(-2) ** 2"), "(-2) ** 2");
+        assert_eq!(display("-SUM(A1:A3) / 2"), "-SUM(A1:A3) / 2");
+        assert_eq!(display("A1 * 10 %"), "A1 * 10%");
+        assert_eq!(display("(A1 + 1)%"), "(A1 + 1)%");
+        assert_eq!(display("-5%%"), "-5%%");
+        assert_eq!(display("\"a \"\"b\"\"\"&C1"), "\"a \"\"b\"\"\" & C1");
+        assert_eq!(display("(\"x\" & 1) = \"x1\""), "\"x\" & 1 = \"x1\"");
+        assert_eq!(display("\"x\" & (1 = 1)"), "\"x\" & (1 = 1)");
+    }
+
+    #[test]
+    fn shifts_references() {
+        let shift = |input: &str, rows, cols| parse(input).unwrap().shift(rows, cols);
+        assert_eq!(
+            shift("-SUM(A1:B2, 3) * C3 + \"A1\"", 1, 2)
+                .unwrap()
+                .to_string(),
+            "-SUM(C2:D3, 3) * E4 + \"A1\""
+        );
+        assert_eq!(
+            shift("SUM($A$1:A1) / $B1 + B$1", 2, 1).unwrap().to_string(),
+            "SUM($A$1:B3) / $B3 + C$1"
+        );
+        assert_eq!(
+            shift("B2 - A1", -1, 0),
+            Err(CellError::reference(
+                "Reference A1 moves outside the sheet when shifted by -1 rows and 0 columns."
+                    .to_string()
+            ))
+        );
     }
 }
@@ -1,235 +1,347 @@
-use crate::{cell::Cell, cell_range::CellRange, sheet::Sheet};
-
-fn std_deviation(data: &Vec<f64>) -> Option<f64> {
-    match data.len() {
-        count if count > 0 => {
-            let avg = data.iter().sum::<f64>() / count as f64;
-            let variance = data
-                .iter()
-                .map(|value| {
-                    let diff = avg - (*value as f64);
-                    diff * diff
-                })
-                .sum::<f64>()
-                / count as f64;
-
-            Some(variance.sqrt())
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{
+    cell_error::CellError,
+    context::Context,
+    dependency_graph::Index,
+    formula::Formula,
+    function_registry::{Argument, Arguments, Arity, SpreadsheetFunction},
+    sheet::Sheet,
+    value::Value,
+};
+
+/// A call of a registered function, e.g. SUM(A1:A3, B5 * 2, 10). Every argument is a formula,
+/// which can also be a cell range.
+#[derive(Clone)]
+pub struct Function {
+    function: Rc<dyn SpreadsheetFunction>,
+    pub args: Vec<Formula>,
+}
+
+impl Function {
+    pub fn new(function: Rc<dyn SpreadsheetFunction>, args: Vec<Formula>) -> Self {
+        Function { function, args }
+    }
+
+    /// Call a function that is not registered, which evaluates to #NAME?.
+    pub fn unknown(name: String, args: Vec<Formula>) -> Self {
+        Function::new(Rc::new(Unknown { name }), args)
+    }
+
+    /// Call a built-in function, for tests.
+    #[cfg(test)]
+    pub fn builtin(name: &str, args: Vec<Formula>) -> Self {
+        let registry = crate::function_registry::FunctionRegistry::default();
+        Function::new(registry.get(name).unwrap(), args)
+    }
+
+    pub fn name(&self) -> &str {
+        self.function.name()
+    }
+
+    /// The same call with the references in its arguments moved, see `Formula::shift`.
+    pub fn shift(&self, rows: isize, cols: isize) -> Result<Self, CellError> {
+        let args = self
+            .args
+            .iter()
+            .map(|arg| arg.shift(rows, cols))
+            .collect::<Result<_, _>>()?;
+        Ok(Function::new(self.function.clone(), args))
+    }
+
+    /// Zero-based (row, col) indices of the cells this function reads, see `Formula::references`.
+    pub fn references(&self, size: (usize, usize)) -> Vec<Index> {
+        self.args
+            .iter()
+            .flat_map(|arg| arg.references(size))
+            .collect()
+    }
+
+    /// Call the function on its arguments. A result that is not a finite number is an error, so no
+    /// function, including custom ones, can put infinity or NaN in a cell.
+    pub fn resolve(&self, resolved: &Sheet) -> Result<Value, CellError> {
+        let out = self.function.call(&Arguments::new(&self.args, resolved))?;
+        match out.as_number() {
+            Some(n) if !n.is_finite() => Err(CellError::num(format!(
+                "{} has no finite result.",
+                self.name()
+            ))),
+            _ => Ok(out),
         }
-        _ => None,
     }
 }
 
-// TODO: all multiple argument support -- probably better through struct of enum FunctionName and vector of args
-// TODO: add all the functions!
-#[derive(Debug, PartialEq, Eq)]
-pub enum Function {
-    Avg(CellRange),
-    Count(CellRange),
-    Max(CellRange),
-    Median(CellRange),
-    Min(CellRange),
-    Stdev(CellRange),
-    Sum(CellRange),
+/// Stands in for a function that is not registered. Its arguments are not evaluated.
+struct Unknown {
+    name: String,
 }
 
-impl Function {
-    fn parse_name(input: &str) -> Option<fn(CellRange) -> Function> {
-        match input {
-            "AVG" => Some(Function::Avg),
-            "COUNT" => Some(Function::Count),
-            "MAX" => Some(Function::Max),
-            "MEDIAN" => Some(Function::Median),
-            "MIN" => Some(Function::Min),
-            "STDEV" => Some(Function::Stdev),
-            "SUM" => Some(Function::Sum),
-            _ => None,
-        }
+impl SpreadsheetFunction for Unknown {
+    fn name(&self) -> &str {
+        &self.name
     }
 
-    pub fn parse(input: &str) -> Result<Self, String> {
-        match input.split(|c| c == '(' || c == ')').collect::<Vec<&str>>()[..] {
-            [function_name, argument, ""] => match (
-                Function::parse_name(function_name.trim()),
-                CellRange::parse(argument.trim()),
-            ) {
-                (Some(function), Ok(cell_range)) => Ok(function(cell_range)),
-                (Some(_), Err(e)) => {
-                    Err(format!("Invalid function argument '{}': '{}'", argument, e))
-                }
-                _ => Err(format!("Function '{}' not supported.", function_name)),
-            },
-            _ => Err("Expected '(' and ')' in function.".to_string()),
-        }
+    fn arity(&self) -> Arity {
+        Arity::at_least(0)
     }
 
-    pub fn resolve(&self, sheet: &Sheet, resolved: &mut Sheet) {
-        let nums_to_str = |nums: &Vec<f64>| {
-            nums.iter()
-                .map(|f| f.to_string())
-                .collect::<Vec<String>>()
-                .join(", ")
-        };
+    fn evaluate(&self, _: &[Argument], _: &Context) -> Result<Value, CellError> {
+        Err(CellError::name(format!(
+            "Function '{}' not supported.",
+            self.name
+        )))
+    }
 
-        let out: f64 = match self {
-            Function::Avg(range) => {
-                println!("  > =AVG({})", range.str);
-                let nums: Vec<f64> = range.resolve(sheet);
-                println!("... AVG({})", nums_to_str(&nums));
-                nums.iter().sum::<f64>() / nums.len() as f64
-            }
-            Function::Count(range) => {
-                println!("  > =COUNT({})", range.str);
-                let nums: Vec<f64> = range.resolve(sheet);
-                println!("... COUNT({})", nums_to_str(&nums));
-                nums.len() as f64
-            }
-            Function::Max(range) => {
-                println!("  > =MAX({})", range.str);
-                let nums: Vec<f64> = range.resolve(sheet);
-                println!("... MAX({})", nums_to_str(&nums));
-                match nums.iter().max_by(|a, b| a.total_cmp(b)) {
-                    Some(max) => *max,
-                    None => f64::NAN,
-                }
-            }
-            Function::Median(range) => {
-                println!("  > =MEDIAN({})", range.str);
-                let mut nums: Vec<f64> = range.resolve(sheet);
-                println!("... MEDIAN({})", nums_to_str(&nums));
-                nums.sort_by(|a, b| a.total_cmp(b));
-                nums[nums.len() / 2]
-            }
-            Function::Min(range) => {
-                println!("  > =MIN({})", range.str);
-                let nums: Vec<f64> = range.resolve(sheet);
-                println!("... MIN({})", nums_to_str(&nums));
-                match nums.iter().min_by(|a, b| a.total_cmp(b)) {
-                    Some(min) => *min,
-                    None => f64::NAN,
-                }
-            }
-            Function::Stdev(range) => {
-                println!("  > =STDEV({})", range.str);
-                let nums: Vec<f64> = range.resolve(sheet);
-                println!("... STDEV({})", nums_to_str(&nums));
-                match std_deviation(&nums) {
-                    Some(stddev) => stddev,
-                    None => f64::NAN,
-                }
-            }
-            Function::Sum(range) => {
-                println!("  > =SUM({})", range.str);
-                let nums: Vec<f64> = range.resolve(sheet);
-                println!("... SUM({})", nums_to_str(&nums));
-                nums.iter().fold(0.0, |acc, n| acc + n)
-            }
-        };
+    fn call(&self, args: &Arguments) -> Result<Value, CellError> {
+        self.evaluate(&[], args.context())
+    }
+}
 
-        println!("... {}\n", out);
-        Cell::Number(out);
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Function")
+            .field("name", &self.name())
+            .field("args", &self.args)
+            .finish()
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name() && self.args == other.args
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let args: Vec<String> = self.args.iter().map(|arg| arg.to_string()).collect();
+        write!(f, "{}({})", self.name(), args.join(", "))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
     use crate::cell_range::CellRange;
+    use crate::formula::Formula;
     use crate::function::Function;
+    use crate::function_registry::FunctionRegistry;
+    use crate::parser::ParseError;
+    use crate::sheet::Sheet;
+
+    fn parse(input: &str) -> Result<Formula, ParseError> {
+        Formula::parse(input, &FunctionRegistry::default())
+    }
 
     #[test]
     fn can_parse_functions() {
         assert_eq!(
-            Function::parse("AVG(A1:A3)").unwrap(),
-            Function::Avg(CellRange::new("A1:A3".to_string(), 1, 1, 3, 1))
+            parse("AVG(A1:A3)").unwrap(),
+            Formula::Function(Function::builtin(
+                "AVG",
+                vec![Formula::CellRange(CellRange::new(
+                    "A1:A3".to_string(),
+                    1,
+                    1,
+                    3,
+                    1
+                ))]
+            ))
         );
         assert_eq!(
-            Function::parse("COUNT(B2:B11)").unwrap(),
-            Function::Count(CellRange::new("B2:B11".to_string(), 2, 2, 11, 2))
+            parse("COUNT(B2:B11)").unwrap(),
+            Formula::Function(Function::builtin(
+                "COUNT",
+                vec![Formula::CellRange(CellRange::new(
+                    "B2:B11".to_string(),
+                    2,
+                    2,
+                    11,
+                    2
+                ))]
+            ))
         );
         assert_eq!(
-            Function::parse("MAX(A2:A8)").unwrap(),
-            Function::Max(CellRange::new("A2:A8".to_string(), 2, 1, 8, 1))
+            parse("MAX(A2:A8)").unwrap(),
+            Formula::Function(Function::builtin(
+                "MAX",
+                vec![Formula::CellRange(CellRange::new(
+                    "A2:A8".to_string(),
+                    2,
+                    1,
+                    8,
+                    1
+                ))]
+            ))
         );
         assert_eq!(
-            Function::parse("MEDIAN(C1:C3)").unwrap(),
-            Function::Median(CellRange::new("C1:C3".to_string(), 1, 3, 3, 3))
+            parse("MEDIAN(C1:C3)").unwrap(),
+            Formula::Function(Function::builtin(
+                "MEDIAN",
+                vec![Formula::CellRange(CellRange::new(
+                    "C1:C3".to_string(),
+                    1,
+                    3,
+                    3,
+                    3
+                ))]
+            ))
         );
         assert_eq!(
-            Function::parse("MIN(A2:A8)").unwrap(),
-            Function::Min(CellRange::new("A2:A8".to_string(), 2, 1, 8, 1))
+            parse("MIN(A2:A8)").unwrap(),
+            Formula::Function(Function::builtin(
+                "MIN",
+                vec![Formula::CellRange(CellRange::new(
+                    "A2:A8".to_string(),
+                    2,
+                    1,
+                    8,
+                    1
+                ))]
+            ))
         );
         assert_eq!(
-            Function::parse("STDEV(Z1:Z10)").unwrap(),
-            Function::Stdev(CellRange::new("Z1:Z10".to_string(), 1, 26, 10, 26))
+            parse("STDEV(Z1:Z10)").unwrap(),
+            Formula::Function(Function::builtin(
+                "STDEV",
+                vec![Formula::CellRange(CellRange::new(
+                    "Z1:Z10".to_string(),
+                    1,
+                    26,
+                    10,
+                    26
+                ))]
+            ))
         );
         assert_eq!(
-            Function::parse("SUM(D2:D4)").unwrap(),
-            Function::Sum(CellRange::new("D2:D4".to_string(), 2, 4, 4, 4))
+            parse("SUM( D2:D4 )").unwrap(),
+            Formula::Function(Function::builtin(
+                "SUM",
+                vec![Formula::CellRange(CellRange::new(
+                    "D2:D4".to_string(),
+                    2,
+                    4,
+                    4,
+                    4
+                ))]
+            ))
         );
     }
 
     #[test]
-    fn handles_missing_brackets() {
+    fn displays_functions() {
+        assert_eq!(parse("SUM( D2:D4 )").unwrap().to_string(), "SUM(D2:D4)");
         assert_eq!(
-            Function::parse(""),
-            Err("Expected '(' and ')' in function.".to_string())
+            parse("SUM(A1:A3,B5*2,MIN(C1:C2,10))").unwrap().to_string(),
+            "SUM(A1:A3, B5 * 2, MIN(C1:C2, 10))"
         );
+    }
+
+    #[test]
+    fn reports_results_that_are_not_finite() {
+        let sheet = Sheet::parse_input(
+            "\"=PMT(1%, 0, 100)\",\"=SUM(10 ** 308, 10 ** 308)\"".to_string(),
+            &FunctionRegistry::default(),
+        )
+        .unwrap();
         assert_eq!(
-            Function::parse("#ERROR#"),
-            Err("Expected '(' and ')' in function.".to_string())
+            sheet.resolve().cells[0],
+            [
+                Cell::Error(CellError::num("PMT has no finite result.".to_string())),
+                Cell::Error(CellError::num("SUM has no finite result.".to_string()))
+            ]
         );
     }
 
     #[test]
-    fn handles_unsupported_functions() {
+    fn handles_missing_brackets() {
         assert_eq!(
-            Function::parse("=AVG(A1:A3)"),
-            Err("Function '=AVG' not supported.".to_string())
+            parse("SUM(D2:D4"),
+            Err(ParseError::new("Unexpected end of formula.".to_string(), 9))
         );
         assert_eq!(
-            Function::parse("=SUM(D2:D4)"),
-            Err("Function '=SUM' not supported.".to_string())
+            parse("SUM D2:D4)"),
+            Err(ParseError::new(
+                "'SUM' must be called with ().".to_string(),
+                0
+            ))
         );
+    }
+
+    #[test]
+    fn handles_unsupported_functions() {
         assert_eq!(
-            Function::parse("IF(1, 2, 3)"),
-            Err("Function 'IF' not supported.".to_string())
+            parse("XYZ(F4,B5:B9,1)").unwrap().to_string(),
+            "XYZ(F4, B5:B9, 1)"
         );
+        let sheet = Sheet::parse_input(
+            "\"=OOPS(1, 2, 3)\",\"=XYZ(F4, B5:B9)\",=1 + OOPS(A1)".to_string(),
+            &FunctionRegistry::default(),
+        )
+        .unwrap();
+        let err = |name: &str| {
+            Cell::Error(CellError::name(format!(
+                "Function '{}' not supported.",
+                name
+            )))
+        };
         assert_eq!(
-            Function::parse("LOOKUP(F4, B5:B9, C5:C9)"),
-            Err("Function 'LOOKUP' not supported.".to_string())
+            sheet.resolve().cells[0],
+            [err("OOPS"), err("XYZ"), err("OOPS")]
         );
+    }
+
+    #[test]
+    fn ignores_case_of_function_names() {
         assert_eq!(
-            Function::parse("DATE(2015, 5, 20)"),
-            Err("Function 'DATE' not supported.".to_string())
+            parse("sum(A1:A3) + Max(1,2)").unwrap().to_string(),
+            "SUM(A1:A3) + MAX(1, 2)"
         );
     }
 
     #[test]
     fn handles_invalid_arguments() {
         assert_eq!(
-            Function::parse("AVG(?)"),
-            Err(
-                "Invalid function argument '?': 'Could not find ':' in cell range '?'.'"
-                    .to_string()
-            )
+            parse("AVG(?)"),
+            Err(ParseError::new("Unexpected character '?'.".to_string(), 4))
+        );
+        assert_eq!(
+            parse("AVG(A1:)"),
+            Err(ParseError::new(
+                "Expected a cell range as argument of 'AVG'.".to_string(),
+                4
+            ))
+        );
+        assert_eq!(
+            parse("AVG(:A1)"),
+            Err(ParseError::new("Unexpected ':'.".to_string(), 4))
         );
         assert_eq!(
-            Function::parse("AVG(A1)"),
-            Err(
-                "Invalid function argument 'A1': 'Could not find ':' in cell range 'A1'.'"
-                    .to_string()
-            )
+            parse("AVG(1,,2)"),
+            Err(ParseError::new("Unexpected ','.".to_string(), 6))
         );
         assert_eq!(
-            Function::parse("AVG(A1:)"),
-            Err("Invalid function argument 'A1:': 'Right side is not a valid cell range: No digit in ''.'".to_string())
+            parse("AVG(A1:A3 + 1)"),
+            Err(ParseError::new(
+                "Expected ')' but found '+'.".to_string(),
+                10
+            ))
         );
         assert_eq!(
-            Function::parse("AVG(:A1)"),
-            Err("Invalid function argument ':A1': 'Left side is not a valid cell range: No digit in ''.'".to_string())
+            parse("AVG(A0:A1)"),
+            Err(ParseError::new(
+                "Left side is not a valid cell range: Invalid row '0' or column '1'.".to_string(),
+                4
+            ))
         );
         assert_eq!(
-            Function::parse("AVG(1A:A1)"),
-            Err("Invalid function argument '1A:A1': 'Left side is not a valid cell range: Could not parse '1A' as column number.'".to_string())
+            parse("AVG(A1:B2:C3)"),
+            Err(ParseError::new(
+                "Expected ')' but found ':'.".to_string(),
+                9
+            ))
         );
     }
 }
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::{
+    builtins, cell::parse_boolean, cell_error::CellError, context::Context, formula::Formula,
+    sheet::Sheet, value::Value,
+};
+
+/// Number of arguments a function accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Arity {
+    pub min: usize,
+    /// No upper bound if None.
+    pub max: Option<usize>,
+}
+
+impl Arity {
+    pub fn exactly(count: usize) -> Self {
+        Arity {
+            min: count,
+            max: Some(count),
+        }
+    }
+
+    pub fn at_least(min: usize) -> Self {
+        Arity { min, max: None }
+    }
+
+    pub fn between(min: usize, max: usize) -> Self {
+        Arity {
+            min,
+            max: Some(max),
+        }
+    }
+
+    /// Check the number of arguments passed to the function `name`.
+    pub fn check(&self, name: &str, count: usize) -> Result<(), String> {
+        let plural = |n: usize| if n == 1 { "argument" } else { "arguments" };
+        let expected = match self.max {
+            Some(max) if count <= max && count >= self.min => return Ok(()),
+            None if count >= self.min => return Ok(()),
+            Some(max) if max == self.min => format!("{} {}", max, plural(max)),
+            Some(max) => format!("{} to {} arguments", self.min, max),
+            None => format!("at least {} {}", self.min, plural(self.min)),
+        };
+        Err(format!(
+            "Function '{}' expects {} but got {}.",
+            name, expected, count
+        ))
+    }
+}
+
+/// An evaluated function argument.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Argument {
+    /// The value of an expression, e.g. 10 or B1 * 2.
+    Value(Value),
+    /// The values of a cell reference or range, row by row, e.g. A1 or A1:C3.
+    Reference(Vec<Vec<Value>>),
+}
+
+impl Argument {
+    /// All values of the argument, in row-major order.
+    pub fn values(&self) -> Vec<&Value> {
+        match self {
+            Argument::Value(value) => vec![value],
+            Argument::Reference(rows) => rows.iter().flatten().collect(),
+        }
+    }
+
+    /// The argument as a single value. A reference to a single cell is the value of that cell, a
+    /// range of multiple cells is an error.
+    pub fn value(&self) -> Result<&Value, CellError> {
+        match self.values()[..] {
+            [value] => Ok(value),
+            _ => Err(CellError::value(
+                "Expected a single value but got a range.".to_string(),
+            )),
+        }
+    }
+
+    /// The argument as a single number. An empty cell counts as 0, TRUE as 1 and FALSE as 0, text
+    /// is an error.
+    pub fn number(&self) -> Result<f64, CellError> {
+        match self.value()? {
+            Value::Boolean(b) => Ok(if *b { 1.0 } else { 0.0 }),
+            Value::Date(n) | Value::Number(n) => Ok(*n),
+            Value::Empty => Ok(0.0),
+            Value::Text(t) => Err(CellError::value(format!("'{}' is not a number.", t))),
+        }
+    }
+
+    /// The argument as a condition. A number is TRUE unless it is 0, an empty cell is FALSE and
+    /// text must be TRUE or FALSE.
+    pub fn boolean(&self) -> Result<bool, CellError> {
+        match self.value()? {
+            Value::Boolean(b) => Ok(*b),
+            Value::Date(n) | Value::Number(n) => Ok(*n != 0.0),
+            Value::Empty => Ok(false),
+            Value::Text(t) => parse_boolean(t)
+                .ok_or_else(|| CellError::value(format!("'{}' is not a boolean.", t))),
+        }
+    }
+}
+
+enum Source<'a> {
+    Formulas(&'a [Formula], &'a Sheet),
+    Evaluated(&'a [Argument]),
+}
+
+/// The arguments of a function call, which are only evaluated when they are read.
+pub struct Arguments<'a> {
+    source: Source<'a>,
+    context: &'a Context,
+}
+
+impl<'a> Arguments<'a> {
+    /// Arguments that get evaluated on the resolved sheet.
+    pub(crate) fn new(formulas: &'a [Formula], resolved: &'a Sheet) -> Self {
+        Arguments {
+            source: Source::Formulas(formulas, resolved),
+            context: &resolved.context,
+        }
+    }
+
+    /// Arguments that have already been evaluated.
+    pub fn evaluated(args: &'a [Argument], context: &'a Context) -> Self {
+        Arguments {
+            source: Source::Evaluated(args),
+            context,
+        }
+    }
+
+    /// The context the function is evaluated in, e.g. to know the current date.
+    pub fn context(&self) -> &Context {
+        self.context
+    }
+
+    pub fn len(&self) -> usize {
+        match self.source {
+            Source::Formulas(formulas, _) => formulas.len(),
+            Source::Evaluated(args) => args.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Evaluate the argument at the index. Cell references and ranges keep the values of all their
+    /// cells, so a function can tell them apart from other expressions.
+    /// The value is recorded as operand of the function in the trace of the sheet.
+    pub fn get(&self, index: usize) -> Result<Argument, CellError> {
+        match self.source {
+            Source::Formulas(formulas, resolved) => {
+                let arg = match &formulas[index] {
+                    Formula::CellPos(pos) => {
+                        let (row, col) = pos.index();
+                        Argument::Reference(vec![vec![resolved.value(row, col)?]])
+                    }
+                    Formula::CellRange(range) => Argument::Reference(range.values(resolved)?),
+                    formula => Argument::Value(formula.resolve(resolved)?),
+                };
+                resolved.trace.operands(|| vec![arg.clone()]);
+                Ok(arg)
+            }
+            Source::Evaluated(args) => Ok(args[index].clone()),
+        }
+    }
+
+    /// Evaluate all arguments, returning the first error.
+    pub fn evaluate_all(&self) -> Result<Vec<Argument>, CellError> {
+        (0..self.len()).map(|i| self.get(i)).collect()
+    }
+}
+
+impl fmt::Display for Argument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Argument::Value(value) => write!(f, "{}", value),
+            Argument::Reference(_) => {
+                let values: Vec<String> = self.values().iter().map(|v| v.to_string()).collect();
+                write!(f, "[{}]", values.join(", "))
+            }
+        }
+    }
+}
+
+/// Flatten the arguments into a list of numbers, the way aggregate functions like SUM read them.
+/// Like in spreadsheets, only the numbers of cell references and ranges are used, while other
+/// arguments must be convertible to a number.
+pub fn numbers(args: &[Argument]) -> Result<Vec<f64>, CellError> {
+    let mut out = Vec::new();
+    for arg in args {
+        match arg {
+            Argument::Value(_) => out.push(arg.number()?),
+            Argument::Reference(_) => {
+                out.extend(arg.values().into_iter().filter_map(Value::as_number))
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// A function that can be called from formulas. Register implementations in a `FunctionRegistry`
+/// to make them available to the parser and evaluator.
+pub trait SpreadsheetFunction {
+    /// Name the function is called by in formulas, e.g. SUM.
+    fn name(&self) -> &str;
+
+    /// Number of arguments the function accepts, checked when a formula is parsed.
+    fn arity(&self) -> Arity;
+
+    /// Compute the result from the evaluated arguments in the context of the call. An error in any
+    /// of the arguments has already been returned before this is called.
+    fn evaluate(&self, args: &[Argument], context: &Context) -> Result<Value, CellError>;
+
+    /// Compute the result from the unevaluated arguments. By default all arguments are evaluated and
+    /// passed to `evaluate`. Override this for functions that don't need all their arguments, like
+    /// IF, so an error in an argument that is not used doesn't end up in the result.
+    fn call(&self, args: &Arguments) -> Result<Value, CellError> {
+        self.evaluate(&args.evaluate_all()?, args.context())
+    }
+}
+
+/// The functions that can be called from formulas, by name.
+#[derive(Clone)]
+pub struct FunctionRegistry {
+    functions: BTreeMap<String, Rc<dyn SpreadsheetFunction>>,
+}
+
+impl FunctionRegistry {
+    /// A registry without any functions. Use `FunctionRegistry::default()` to start from the
+    /// built-in functions.
+    pub fn empty() -> Self {
+        FunctionRegistry {
+            functions: BTreeMap::new(),
+        }
+    }
+
+    /// Add a function, replacing any function that was registered under the same name. Names
+    /// ignore case, like in spreadsheets.
+    pub fn register(&mut self, function: impl SpreadsheetFunction + 'static) {
+        self.functions
+            .insert(function.name().to_ascii_uppercase(), Rc::new(function));
+    }
+
+    /// The function called `name`, ignoring case.
+    pub fn get(&self, name: &str) -> Option<Rc<dyn SpreadsheetFunction>> {
+        self.functions.get(&name.to_ascii_uppercase()).cloned()
+    }
+
+    /// Names of all registered functions, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.functions.keys().map(|name| name.as_str())
+    }
+}
+
+impl Default for FunctionRegistry {
+    /// A registry with the built-in functions.
+    fn default() -> Self {
+        let mut registry = FunctionRegistry::empty();
+        builtins::register(&mut registry);
+        registry
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cell_error::CellError;
+    use crate::context::Context;
+    use crate::function_registry::{
+        numbers, Argument, Arity, FunctionRegistry, SpreadsheetFunction,
+    };
+    use crate::value::Value;
+
+    struct Double;
+
+    impl SpreadsheetFunction for Double {
+        fn name(&self) -> &str {
+            "DOUBLE"
+        }
+
+        fn arity(&self) -> Arity {
+            Arity::exactly(1)
+        }
+
+        fn evaluate(&self, args: &[Argument], _: &Context) -> Result<Value, CellError> {
+            Ok(Value::Number(args[0].number()? * 2.0))
+        }
+    }
+
+    #[test]
+    fn checks_arity() {
+        assert_eq!(Arity::exactly(2).check("F", 2), Ok(()));
+        assert_eq!(Arity::at_least(1).check("F", 5), Ok(()));
+        assert_eq!(Arity::between(1, 3).check("F", 3), Ok(()));
+        assert_eq!(
+            Arity::exactly(1).check("F", 2),
+            Err("Function 'F' expects 1 argument but got 2.".to_string())
+        );
+        assert_eq!(
+            Arity::at_least(1).check("SUM", 0),
+            Err("Function 'SUM' expects at least 1 argument but got 0.".to_string())
+        );
+        assert_eq!(
+            Arity::between(2, 3).check("F", 1),
+            Err("Function 'F' expects 2 to 3 arguments but got 1.".to_string())
+        );
+    }
+
+    #[test]
+    fn registers_functions() {
+        let mut registry = FunctionRegistry::empty();
+        assert!(registry.get("DOUBLE").is_none());
+
+        registry.register(Double);
+        let double = registry.get("DOUBLE").unwrap();
+        assert_eq!(
+            double.evaluate(&[Argument::Value(Value::Number(21.0))], &Context::default()),
+            Ok(Value::Number(42.0))
+        );
+        assert_eq!(registry.names().collect::<Vec<&str>>(), vec!["DOUBLE"]);
+        assert_eq!(registry.get("Double").unwrap().name(), "DOUBLE");
+    }
+
+    #[test]
+    fn registers_builtins_by_default() {
+        let registry = FunctionRegistry::default();
+        for name in ["AVG", "IF", "SUM", "VLOOKUP"] {
+            assert_eq!(registry.get(name).unwrap().name(), name);
+        }
+        assert!(registry.get("DOUBLE").is_none());
+    }
+
+    #[test]
+    fn evaluates_builtins_in_the_given_context() {
+        let registry = FunctionRegistry::default();
+        let context = Context::with_today(2015, 5, 20);
+        assert_eq!(
+            registry.get("TODAY").unwrap().evaluate(&[], &context),
+            Ok(Value::Date(context.today))
+        );
+        assert_eq!(
+            registry.get("IF").unwrap().evaluate(
+                &[
+                    Argument::Value(Value::Boolean(false)),
+                    Argument::Value(Value::Number(1.0)),
+                    Argument::Value(Value::Number(2.0)),
+                ],
+                &context
+            ),
+            Ok(Value::Number(2.0))
+        );
+    }
+
+    #[test]
+    fn flattens_arguments_to_numbers() {
+        let args = [
+            Argument::Value(Value::Boolean(true)),
+            Argument::Reference(vec![
+                vec![Value::Number(2.0), Value::Text("x".to_string())],
+                vec![Value::Empty, Value::Number(3.0)],
+            ]),
+        ];
+        assert_eq!(numbers(&args), Ok(vec![1.0, 2.0, 3.0]));
+        assert_eq!(
+            numbers(&[Argument::Value(Value::Text("x".to_string()))]),
+            Err(CellError::value("'x' is not a number.".to_string()))
+        );
+    }
+}
@@ -1,19 +1,46 @@
+mod builtins;
 mod cell;
+mod cell_error;
 mod cell_pos;
 mod cell_range;
+mod context;
+mod csv;
+mod date;
+mod dependency_graph;
 mod expression;
 mod formula;
 mod function;
-mod number_or_cell_pos;
+mod function_registry;
+mod parser;
 mod sheet;
+mod tokenizer;
+mod trace;
+mod value;
 
-use crate::sheet::Sheet;
+pub use crate::cell::Cell;
+pub use crate::cell_error::{CellError, ErrorKind};
+pub use crate::cell_pos::CellPos;
+pub use crate::context::Context;
+pub use crate::csv::{CsvError, CsvLayout, CsvOptions};
+pub use crate::function_registry::{
+    numbers, Argument, Arguments, Arity, FunctionRegistry, SpreadsheetFunction,
+};
+pub use crate::sheet::Sheet;
+pub use crate::trace::{Step, Trace};
+pub use crate::value::Value;
 
-pub fn run(input: String) -> String {
-    let sheet = Sheet::parse_input(input);
-    print!("{}\n\n", sheet);
-    let resolved = sheet.resolve();
-    let out = resolved.to_string();
-    print!("{}\n\n", out);
-    out
+pub fn run(input: String) -> Result<String, CsvError> {
+    run_with(input, &FunctionRegistry::default(), &Context::default())
+}
+
+/// Like `run`, but formulas can call the functions in the given registry instead of only the
+/// built-in functions, and are evaluated in the given context.
+pub fn run_with(
+    input: String,
+    registry: &FunctionRegistry,
+    context: &Context,
+) -> Result<String, CsvError> {
+    let mut sheet = Sheet::parse_input(input, registry)?;
+    sheet.context = context.clone();
+    Ok(sheet.resolve().to_string())
 }
@@ -1,13 +1,308 @@
-// use std::fs;
-
-fn main() {
-    let input = "descr  ,amount, unit_price,total_price
-    Cookies,     4,       2.95,=B2 * C2
-    Coffee ,     1,=9.60 * 0.8,0
-    Water  ,     2,       1.20,0
-    Total  ,      ,           ,=SUM(D2:D4)"
-        .to_string();
-    // let input =
-    // fs::read_to_string("examples/arithmetic_with_literals.csv").expect("Could not load file.");
-    simple_spreadsheet_engine::run(input);
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    process::ExitCode,
+};
+
+use simple_spreadsheet_engine::{Cell, CellPos, CsvLayout, CsvOptions, FunctionRegistry, Sheet};
+
+const USAGE: &str = "Usage: simple_spreadsheet_engine [OPTIONS] [INPUT]
+
+Evaluate the spreadsheet in the CSV file INPUT, or standard input if INPUT is - or left out, and
+write the result as CSV.
+
+Options:
+  -o, --output FILE            Write to FILE instead of standard output
+  -d, --delimiter CHAR         Delimiter between the cells of the input [default: ,]
+  -q, --quote CHAR             Quote around cells that contain the delimiter [default: \"]
+      --output-delimiter CHAR  Delimiter between the cells of the output [default: the input delimiter]
+  -f, --format FORMAT          Layout of the output: canonical or aligned [default: canonical]
+      --formulas               Write the formulas instead of their values
+      --explain CELL           Write how CELL, e.g. C4, got its value instead of the sheet
+  -h, --help                   Print this help
+
+CHAR can also be 'tab' for a tab character.
+
+Exit status: 0 on success, 1 if a cell (or the explained CELL) evaluates to an error, 2 if the
+input can't be read.";
+
+/// What the command line asks for.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    /// The path of the input, None for standard input.
+    input: Option<String>,
+    /// The path of the output, None for standard output.
+    output: Option<String>,
+    read: CsvOptions,
+    write: CsvOptions,
+    formulas: bool,
+    /// The cell to explain instead of writing the sheet.
+    explain: Option<CellPos>,
+    help: bool,
+}
+
+/// Parse the command line arguments, without the name of the program.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut output_delimiter = None;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Option '{}' expects a value.", arg))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => options.output = Some(value()?),
+            "-d" | "--delimiter" => options.read.delimiter = parse_char(&value()?)?,
+            "-q" | "--quote" => options.read.quote = parse_char(&value()?)?,
+            "--output-delimiter" => output_delimiter = Some(parse_char(&value()?)?),
+            "-f" | "--format" => {
+                options.write.layout = match value()?.as_str() {
+                    "canonical" => CsvLayout::Canonical,
+                    "aligned" => CsvLayout::Aligned,
+                    format => {
+                        return Err(format!(
+                            "Unknown format '{}'. Expected canonical or aligned.",
+                            format
+                        ))
+                    }
+                }
+            }
+            "--formulas" => options.formulas = true,
+            "--explain" => {
+                let cell = value()?;
+                options.explain = Some(
+                    CellPos::parse(&cell)
+                        .map_err(|_| format!("Expected a cell like C4 but got '{}'.", cell))?,
+                );
+            }
+            "-h" | "--help" => options.help = true,
+            "-" => options.input = None,
+            _ if arg.starts_with('-') => return Err(format!("Unknown option '{}'.", arg)),
+            _ if options.input.is_some() => {
+                return Err(format!("Unexpected argument '{}'.", arg));
+            }
+            _ => options.input = Some(arg),
+        }
+    }
+
+    options.write.delimiter = output_delimiter.unwrap_or(options.read.delimiter);
+    options.write.quote = options.read.quote;
+    Ok(options)
+}
+
+/// A single character, or 'tab'.
+fn parse_char(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    match (value, chars.next(), chars.next()) {
+        ("tab" | "\\t", _, _) => Ok('\t'),
+        (_, Some(c), None) => Ok(c),
+        _ => Err(format!("Expected a single character but got '{}'.", value)),
+    }
+}
+
+/// Evaluate the input as the options ask, giving the output and whether a cell evaluated to an
+/// error.
+fn evaluate(input: &str, options: &Options) -> Result<(String, bool), String> {
+    let sheet = Sheet::parse_csv(input, &options.read, &FunctionRegistry::default())
+        .map_err(|e| format!("Could not read the input: {}", e))?;
+    if let Some(pos) = &options.explain {
+        return explain(&sheet.resolve_traced(), pos);
+    }
+    let sheet = if options.formulas {
+        sheet
+    } else {
+        sheet.resolve()
+    };
+    let has_errors = sheet
+        .cells
+        .iter()
+        .flatten()
+        .any(|cell| matches!(cell, Cell::Error(_)));
+    Ok((sheet.write(&options.write), has_errors))
+}
+
+/// Explain the value of a cell of the resolved sheet, giving the explanation and whether the cell
+/// evaluated to an error.
+fn explain(resolved: &Sheet, pos: &CellPos) -> Result<(String, bool), String> {
+    let (row, col) = pos.index();
+    let cell = resolved.cell(row, col).map_err(|e| e.detail)?;
+    Ok((
+        resolved.explain(row, col),
+        matches!(cell, Some(Cell::Error(_))),
+    ))
+}
+
+fn run(options: &Options) -> Result<bool, String> {
+    let input = match &options.input {
+        Some(path) => {
+            fs::read_to_string(path).map_err(|e| format!("Could not read '{}': {}", path, e))?
+        }
+        None => {
+            let mut input = String::new();
+            io::stdin()
+                .read_to_string(&mut input)
+                .map_err(|e| format!("Could not read standard input: {}", e))?;
+            input
+        }
+    };
+
+    let (output, has_errors) = evaluate(&input, options)?;
+    match &options.output {
+        Some(path) => fs::write(path, output + "\n")
+            .map_err(|e| format!("Could not write '{}': {}", path, e))?,
+        None => writeln!(io::stdout(), "{}", output)
+            .map_err(|e| format!("Could not write standard output: {}", e))?,
+    }
+    Ok(has_errors)
+}
+
+fn main() -> ExitCode {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    match run(&options) {
+        Ok(false) => ExitCode::SUCCESS,
+        Ok(true) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{evaluate, parse_args, Options};
+    use simple_spreadsheet_engine::{CsvLayout, CsvOptions};
+
+    fn args(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn parses_options() {
+        assert_eq!(args(&[]), Ok(Options::default()));
+        assert_eq!(
+            args(&[
+                "-d",
+                ";",
+                "in.csv",
+                "--format",
+                "aligned",
+                "-o",
+                "out.csv",
+                "--formulas"
+            ]),
+            Ok(Options {
+                input: Some("in.csv".to_string()),
+                output: Some("out.csv".to_string()),
+                read: CsvOptions {
+                    delimiter: ';',
+                    ..CsvOptions::default()
+                },
+                write: CsvOptions {
+                    delimiter: ';',
+                    layout: CsvLayout::Aligned,
+                    ..CsvOptions::default()
+                },
+                formulas: true,
+                explain: None,
+                help: false,
+            })
+        );
+        assert_eq!(
+            args(&["--delimiter", "tab", "--output-delimiter", ","])
+                .map(|o| (o.read.delimiter, o.write.delimiter)),
+            Ok(('\t', ','))
+        );
+        assert_eq!(
+            args(&["--explain", "c4"]).map(|o| o.explain.map(|pos| pos.index())),
+            Ok(Some((3, 2)))
+        );
+    }
+
+    #[test]
+    fn reports_invalid_options() {
+        assert_eq!(
+            args(&["--format", "pretty"]),
+            Err("Unknown format 'pretty'. Expected canonical or aligned.".to_string())
+        );
+        assert_eq!(
+            args(&["-d"]),
+            Err("Option '-d' expects a value.".to_string())
+        );
+        assert_eq!(
+            args(&["-d", "::"]),
+            Err("Expected a single character but got '::'.".to_string())
+        );
+        assert_eq!(
+            args(&["--explain", "4C"]),
+            Err("Expected a cell like C4 but got '4C'.".to_string())
+        );
+        assert_eq!(
+            args(&["--verbose"]),
+            Err("Unknown option '--verbose'.".to_string())
+        );
+        assert_eq!(
+            args(&["a.csv", "b.csv"]),
+            Err("Unexpected argument 'b.csv'.".to_string())
+        );
+    }
+
+    #[test]
+    fn evaluates_values_or_formulas() {
+        let input = "1, 2, =A1 + B1\n\"=SUM(A1:C1, 1)\", =1 / 0";
+        assert_eq!(
+            evaluate(input, &args(&[]).unwrap()),
+            Ok((
+                "1,2,3\n7,#DIV/0!: Cannot divide 1 by zero.".to_string(),
+                true
+            ))
+        );
+        assert_eq!(
+            evaluate(input, &args(&["--formulas"]).unwrap()),
+            Ok(("1,2,=A1 + B1\n\"=SUM(A1:C1, 1)\",=1 / 0".to_string(), false))
+        );
+        assert_eq!(
+            evaluate("\"a", &args(&[]).unwrap()),
+            Err(
+                "Could not read the input: Quoted cell is not closed. (line 1, column 1)"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn explains_cells() {
+        let input = "1, 2, =A1 + B1\n\"=SUM(A1:C1, 1)\", =1 / 0";
+        assert_eq!(
+            evaluate(input, &args(&["--explain", "A2"]).unwrap()),
+            Ok((
+                "A2: =SUM(A1:C1, 1) = 7 (from [1, 2, 3], 1)".to_string(),
+                false
+            ))
+        );
+        assert_eq!(
+            evaluate(input, &args(&["--explain", "B2"]).unwrap()),
+            Ok((
+                "B2: =1 / 0 = #DIV/0!: Cannot divide 1 by zero. (from 1, 0)".to_string(),
+                true
+            ))
+        );
+        assert_eq!(
+            evaluate(input, &args(&["--explain", "D1"]).unwrap()),
+            Err("Cell D1 is outside the sheet.".to_string())
+        );
+    }
 }
@@ -0,0 +1,564 @@
+use std::fmt;
+
+use crate::cell::parse_boolean;
+use crate::cell_error::ErrorKind;
+use crate::cell_pos::CellPos;
+use crate::cell_range::CellRange;
+use crate::formula::{
+    ArithmeticOperator, ComparisonOperator, Formula, Operator, UnaryOperator, UNARY_PRECEDENCE,
+};
+use crate::function::Function;
+use crate::function_registry::FunctionRegistry;
+use crate::tokenizer::{tokenize, Token};
+
+/// Error while parsing a formula, with the column (zero-based character offset) of the offending token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub message: String,
+    pub column: usize,
+    /// The kind of error the cell with the formula resolves to.
+    pub kind: ErrorKind,
+}
+
+impl ParseError {
+    pub fn new(message: String, column: usize) -> Self {
+        ParseError {
+            message,
+            column,
+            kind: ErrorKind::Parse,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (column {})", self.message, self.column)
+    }
+}
+
+/// Recursive descent parser for formulas. Binary operators are parsed through precedence climbing,
+/// see `Operator::precedence`.
+/// Function calls are looked up in the registry.
+pub struct Parser<'a> {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    /// Column right after the input, reported when the input ends unexpectedly.
+    end: usize,
+    registry: &'a FunctionRegistry,
+}
+
+impl<'a> Parser<'a> {
+    pub fn parse(input: &str, registry: &'a FunctionRegistry) -> Result<Formula, ParseError> {
+        let mut parser = Parser {
+            tokens: tokenize(input)?,
+            position: 0,
+            end: input.chars().count(),
+            registry,
+        };
+
+        let formula = parser.expression(0)?;
+        match parser.peek() {
+            None => Ok(formula),
+            Some(token) => Err(parser.unexpected(token)),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    /// Column of the next token, or of the end of the input.
+    fn column(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(column, _)| *column)
+    }
+
+    fn next(&mut self) -> Result<Token, ParseError> {
+        match self.tokens.get(self.position) {
+            Some((_, token)) => {
+                self.position += 1;
+                Ok(token.clone())
+            }
+            None => Err(ParseError::new(
+                "Unexpected end of formula.".to_string(),
+                self.end,
+            )),
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ParseError> {
+        let column = self.column();
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(ParseError::new(
+                format!("Expected '{}' but found '{}'.", expected, token),
+                column,
+            )),
+        }
+    }
+
+    fn unexpected(&self, token: &Token) -> ParseError {
+        ParseError::new(format!("Unexpected '{}'.", token), self.column())
+    }
+
+    fn binary_operator(&self) -> Option<Operator> {
+        let op = match self.peek()? {
+            Token::DoubleStar => Operator::ArithmeticOperator(ArithmeticOperator::Exponentiation),
+            Token::Minus => Operator::ArithmeticOperator(ArithmeticOperator::Subtraction),
+            Token::Plus => Operator::ArithmeticOperator(ArithmeticOperator::Addition),
+            Token::Slash => Operator::ArithmeticOperator(ArithmeticOperator::Division),
+            Token::Star => Operator::ArithmeticOperator(ArithmeticOperator::Multiplication),
+            Token::Equal => Operator::ComparisonOperator(ComparisonOperator::Equal),
+            Token::GreaterThan => Operator::ComparisonOperator(ComparisonOperator::GreaterThan),
+            Token::GreaterThanOrEqual => {
+                Operator::ComparisonOperator(ComparisonOperator::GreaterThanOrEqual)
+            }
+            Token::LessThan => Operator::ComparisonOperator(ComparisonOperator::LessThan),
+            Token::LessThanOrEqual => {
+                Operator::ComparisonOperator(ComparisonOperator::LessThanOrEqual)
+            }
+            Token::NotEqual => Operator::ComparisonOperator(ComparisonOperator::NotEqual),
+            Token::Ampersand => Operator::TextConcatenationOperator,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// Parse a chain of binary operations whose operators bind at least as strong as `min_precedence`.
+    fn expression(&mut self, min_precedence: u8) -> Result<Formula, ParseError> {
+        let mut lhs = self.unary()?;
+
+        while let Some(op) = self.binary_operator() {
+            let precedence = op.precedence();
+            if precedence < min_precedence {
+                break;
+            }
+            self.position += 1;
+
+            let rhs = if op.is_right_associative() {
+                self.expression(precedence)?
+            } else {
+                self.expression(precedence + 1)?
+            };
+            lhs = Formula::new(op, lhs, rhs);
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Formula, ParseError> {
+        let op = match self.peek() {
+            Some(Token::Minus) => UnaryOperator::Minus,
+            Some(Token::Plus) => UnaryOperator::Plus,
+            _ => return self.postfix(),
+        };
+        self.position += 1;
+
+        // Everything that binds tighter than the unary operator belongs to its operand.
+        let operand = self.expression(UNARY_PRECEDENCE + 1)?;
+        Ok(Formula::Unary(op, Box::new(operand)))
+    }
+
+    /// A primary expression followed by any number of % operators, e.g. 15% or A1%%.
+    fn postfix(&mut self) -> Result<Formula, ParseError> {
+        let mut formula = self.primary()?;
+        while self.peek() == Some(&Token::Percent) {
+            self.position += 1;
+            formula = Formula::Unary(UnaryOperator::Percent, Box::new(formula));
+        }
+        Ok(formula)
+    }
+
+    fn primary(&mut self) -> Result<Formula, ParseError> {
+        let column = self.column();
+        match self.next()? {
+            Token::Number(n) => Ok(Formula::Number(n)),
+            Token::Text(t) => Ok(Formula::Text(t)),
+            Token::LeftParen => {
+                let formula = self.expression(0)?;
+                self.expect(Token::RightParen)?;
+                Ok(formula)
+            }
+            Token::Word(word) if self.peek() == Some(&Token::LeftParen) => {
+                self.function(word, column)
+            }
+            Token::Word(word) => match parse_boolean(&word) {
+                Some(b) => Ok(Formula::Boolean(b)),
+                None if CellPos::parse(&word).is_err() && self.registry.get(&word).is_some() => {
+                    Err(ParseError::new(
+                        format!("'{}' must be called with ().", word),
+                        column,
+                    ))
+                }
+                None => Parser::cell_pos(&word, column).map(Formula::CellPos),
+            },
+            token => {
+                self.position -= 1;
+                Err(self.unexpected(&token))
+            }
+        }
+    }
+
+    /// A function call. An unknown function is not a parse error but evaluates to #NAME?, so
+    /// ISERROR and IFERROR can catch it.
+    fn function(&mut self, name: String, column: usize) -> Result<Formula, ParseError> {
+        self.expect(Token::LeftParen)?;
+        let mut args = Vec::new();
+        if self.peek() != Some(&Token::RightParen) {
+            args.push(self.argument(&name)?);
+            while self.peek() == Some(&Token::Comma) {
+                self.position += 1;
+                args.push(self.argument(&name)?);
+            }
+        }
+        self.expect(Token::RightParen)?;
+        let function = match self.registry.get(&name) {
+            Some(function) => function,
+            None => return Ok(Formula::Function(Function::unknown(name, args))),
+        };
+        function
+            .arity()
+            .check(&name, args.len())
+            .map_err(|e| ParseError::new(e, column))?;
+        Ok(Formula::Function(Function::new(function, args)))
+    }
+
+    /// A function argument: a cell range like A1:B3, or any other expression.
+    fn argument(&mut self, function: &str) -> Result<Formula, ParseError> {
+        match self.tokens[self.position..] {
+            [(_, Token::Word(_)), (_, Token::Colon), ..] => {
+                self.cell_range(function).map(Formula::CellRange)
+            }
+            _ => self.expression(0),
+        }
+    }
+
+    fn cell_range(&mut self, function: &str) -> Result<CellRange, ParseError> {
+        let column = self.column();
+        let tokens = &self.tokens[self.position..];
+        match tokens {
+            [(_, Token::Word(start)), (_, Token::Colon), (_, Token::Word(end)), ..] => {
+                let range = CellRange::parse(&format!("{}:{}", start, end))
+                    .map_err(|e| ParseError::new(e, column))?;
+                self.position += 3;
+                Ok(range)
+            }
+            _ => Err(ParseError::new(
+                format!("Expected a cell range as argument of '{}'.", function),
+                column,
+            )),
+        }
+    }
+
+    fn cell_pos(word: &str, column: usize) -> Result<CellPos, ParseError> {
+        CellPos::parse(word).map_err(|_| {
+            ParseError::new(format!("'{}' is not a valid cell reference.", word), column)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cell_pos::CellPos;
+    use crate::cell_range::CellRange;
+    use crate::formula::{
+        ArithmeticOperator, ComparisonOperator, Formula, Operator, UnaryOperator,
+    };
+    use crate::function::Function;
+    use crate::function_registry::FunctionRegistry;
+    use crate::parser::{ParseError, Parser};
+
+    fn parse(input: &str) -> Result<Formula, ParseError> {
+        Parser::parse(input, &FunctionRegistry::default())
+    }
+
+    fn binary(op: ArithmeticOperator, left: Formula, right: Formula) -> Formula {
+        Formula::new(Operator::ArithmeticOperator(op), left, right)
+    }
+
+    fn minus(operand: Formula) -> Formula {
+        Formula::Unary(UnaryOperator::Minus, Box::new(operand))
+    }
+
+    fn cell(str: &str, row: usize, col: usize) -> Formula {
+        Formula::CellPos(CellPos::new(str.to_string(), row, col))
+    }
+
+    #[test]
+    #[allow(clippy::approx_constant)]
+    fn can_parse_operands() {
+        assert_eq!(parse("1").unwrap(), Formula::Number(1.0));
+        assert_eq!(parse("-1").unwrap(), minus(Formula::Number(1.0)));
+        assert_eq!(parse("3.141592").unwrap(), Formula::Number(3.141592));
+        assert_eq!(parse("A1").unwrap(), cell("A1", 1, 1));
+        assert_eq!(parse("ZA99").unwrap(), cell("ZA99", 99, 677));
+        assert_eq!(parse(" ( (B2) ) ").unwrap(), cell("B2", 2, 2));
+    }
+
+    #[test]
+    fn respects_precedence() {
+        assert_eq!(
+            parse("A1 + B1 * 2").unwrap(),
+            binary(
+                ArithmeticOperator::Addition,
+                cell("A1", 1, 1),
+                binary(
+                    ArithmeticOperator::Multiplication,
+                    cell("B1", 1, 2),
+                    Formula::Number(2.0)
+                )
+            )
+        );
+        assert_eq!(
+            parse("(A1 + 1) / 2").unwrap(),
+            binary(
+                ArithmeticOperator::Division,
+                binary(
+                    ArithmeticOperator::Addition,
+                    cell("A1", 1, 1),
+                    Formula::Number(1.0)
+                ),
+                Formula::Number(2.0)
+            )
+        );
+        assert_eq!(
+            parse("-2 ** 2").unwrap(),
+            minus(binary(
+                ArithmeticOperator::Exponentiation,
+                Formula::Number(2.0),
+                Formula::Number(2.0)
+            ))
+        );
+        assert_eq!(
+            parse("-1 + 1").unwrap(),
+            binary(
+                ArithmeticOperator::Addition,
+                minus(Formula::Number(1.0)),
+                Formula::Number(1.0)
+            )
+        );
+        assert_eq!(
+            parse("2 * -A1").unwrap(),
+            binary(
+                ArithmeticOperator::Multiplication,
+                Formula::Number(2.0),
+                minus(cell("A1", 1, 1))
+            )
+        );
+    }
+
+    #[test]
+    fn respects_associativity() {
+        assert_eq!(
+            parse("8 - 4 - 2").unwrap(),
+            binary(
+                ArithmeticOperator::Subtraction,
+                binary(
+                    ArithmeticOperator::Subtraction,
+                    Formula::Number(8.0),
+                    Formula::Number(4.0)
+                ),
+                Formula::Number(2.0)
+            )
+        );
+        assert_eq!(
+            parse("2 ** 3 ** 2").unwrap(),
+            binary(
+                ArithmeticOperator::Exponentiation,
+                Formula::Number(2.0),
+                binary(
+                    ArithmeticOperator::Exponentiation,
+                    Formula::Number(3.0),
+                    Formula::Number(2.0)
+                )
+            )
+        );
+        assert_eq!(
+            parse("2 ** -1").unwrap(),
+            binary(
+                ArithmeticOperator::Exponentiation,
+                Formula::Number(2.0),
+                minus(Formula::Number(1.0))
+            )
+        );
+    }
+
+    #[test]
+    fn can_parse_percentages() {
+        let percent = |operand| Formula::Unary(UnaryOperator::Percent, Box::new(operand));
+        assert_eq!(
+            parse("A1 * 15%").unwrap(),
+            binary(
+                ArithmeticOperator::Multiplication,
+                cell("A1", 1, 1),
+                percent(Formula::Number(15.0))
+            )
+        );
+        assert_eq!(
+            parse("-5%%").unwrap(),
+            minus(percent(percent(Formula::Number(5.0))))
+        );
+        assert_eq!(
+            parse("2 ** 50%").unwrap(),
+            binary(
+                ArithmeticOperator::Exponentiation,
+                Formula::Number(2.0),
+                percent(Formula::Number(50.0))
+            )
+        );
+    }
+
+    #[test]
+    fn can_parse_comparisons() {
+        let compare = |op, left, right| Formula::new(Operator::ComparisonOperator(op), left, right);
+        assert_eq!(
+            parse("B2 > 100").unwrap(),
+            compare(
+                ComparisonOperator::GreaterThan,
+                cell("B2", 2, 2),
+                Formula::Number(100.0)
+            )
+        );
+        assert_eq!(
+            parse("A1 + 1 <> B1 * 2").unwrap(),
+            compare(
+                ComparisonOperator::NotEqual,
+                binary(
+                    ArithmeticOperator::Addition,
+                    cell("A1", 1, 1),
+                    Formula::Number(1.0)
+                ),
+                binary(
+                    ArithmeticOperator::Multiplication,
+                    cell("B1", 1, 2),
+                    Formula::Number(2.0)
+                )
+            )
+        );
+        assert_eq!(
+            parse("1 < 2 = true").unwrap(),
+            compare(
+                ComparisonOperator::Equal,
+                compare(
+                    ComparisonOperator::LessThan,
+                    Formula::Number(1.0),
+                    Formula::Number(2.0)
+                ),
+                Formula::Boolean(true)
+            )
+        );
+        assert_eq!(parse("FALSE").unwrap(), Formula::Boolean(false));
+    }
+
+    #[test]
+    fn can_parse_text_concatenation() {
+        let concat = |left, right| Formula::new(Operator::TextConcatenationOperator, left, right);
+        assert_eq!(
+            parse("\"Total: \" & D5").unwrap(),
+            concat(Formula::Text("Total: ".to_string()), cell("D5", 5, 4))
+        );
+        assert_eq!(
+            parse("A1 & \"-\" & B1 + 1 = \"x\"").unwrap(),
+            Formula::new(
+                Operator::ComparisonOperator(ComparisonOperator::Equal),
+                concat(
+                    concat(cell("A1", 1, 1), Formula::Text("-".to_string())),
+                    binary(
+                        ArithmeticOperator::Addition,
+                        cell("B1", 1, 2),
+                        Formula::Number(1.0)
+                    )
+                ),
+                Formula::Text("x".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn can_parse_functions_as_operands() {
+        assert_eq!(
+            parse("SUM(A1:A3) / COUNT(A1:A3)").unwrap(),
+            binary(
+                ArithmeticOperator::Division,
+                Formula::Function(Function::builtin(
+                    "SUM",
+                    vec![Formula::CellRange(CellRange::new(
+                        "A1:A3".to_string(),
+                        1,
+                        1,
+                        3,
+                        1
+                    ))]
+                )),
+                Formula::Function(Function::builtin(
+                    "COUNT",
+                    vec![Formula::CellRange(CellRange::new(
+                        "A1:A3".to_string(),
+                        1,
+                        1,
+                        3,
+                        1
+                    ))]
+                ))
+            )
+        );
+    }
+
+    #[test]
+    fn can_parse_function_arguments() {
+        assert_eq!(
+            parse("SUM(A1:A3, -B5, MAX(C1, 10))").unwrap(),
+            Formula::Function(Function::builtin(
+                "SUM",
+                vec![
+                    Formula::CellRange(CellRange::new("A1:A3".to_string(), 1, 1, 3, 1)),
+                    minus(cell("B5", 5, 2)),
+                    Formula::Function(Function::builtin(
+                        "MAX",
+                        vec![cell("C1", 1, 3), Formula::Number(10.0)]
+                    ))
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn reports_column_of_offending_token() {
+        let err = |message: &str, column| Err(ParseError::new(message.to_string(), column));
+        assert_eq!(parse(""), err("Unexpected end of formula.", 0));
+        assert_eq!(parse("?"), err("Unexpected character '?'.", 0));
+        assert_eq!(parse("=123"), err("Unexpected '='.", 0));
+        assert_eq!(parse("1 >"), err("Unexpected end of formula.", 3));
+        assert_eq!(parse("1 => 2"), err("Unexpected '>'.", 3));
+        assert_eq!(parse("Z0"), err("'Z0' is not a valid cell reference.", 0));
+        assert_eq!(parse("A1:"), err("Unexpected ':'.", 2));
+        assert_eq!(parse(":A1"), err("Unexpected ':'.", 0));
+        assert_eq!(parse("1A:A1"), err("Unexpected 'A'.", 1));
+        assert_eq!(
+            parse("1.23 ++ 456"),
+            Ok(binary(
+                ArithmeticOperator::Addition,
+                Formula::Number(1.23),
+                Formula::Unary(UnaryOperator::Plus, Box::new(Formula::Number(456.0)))
+            ))
+        );
+        assert_eq!(parse("1.23 ** * 456"), err("Unexpected '*'.", 8));
+        assert_eq!(parse("+ A1 B2"), err("Unexpected 'B2'.", 5));
+        assert_eq!(parse("(1 + 2"), err("Unexpected end of formula.", 6));
+        assert_eq!(parse("1 + 2)"), err("Unexpected ')'.", 5));
+        assert_eq!(
+            parse("nope + 1"),
+            err("'nope' is not a valid cell reference.", 0)
+        );
+        assert_eq!(parse("1 + OOPS(1, )"), err("Unexpected ')'.", 12));
+        assert_eq!(parse("AVG(?)"), err("Unexpected character '?'.", 4));
+        assert_eq!(parse("AVG(A1, )"), err("Unexpected ')'.", 8));
+        assert_eq!(
+            parse("1 + SUM()"),
+            err("Function 'SUM' expects at least 1 argument but got 0.", 4)
+        );
+        assert_eq!(parse("AVG"), err("'AVG' must be called with ().", 0));
+        assert_eq!(parse("1 + sum"), err("'sum' must be called with ().", 4));
+    }
+}
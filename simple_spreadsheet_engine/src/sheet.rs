@@ -1,43 +1,225 @@
 use std::fmt;
 
 use crate::cell::Cell;
+use crate::cell_error::CellError;
+use crate::cell_pos::CellPos;
+use crate::context::Context;
+use crate::csv::{self, CsvError, CsvOptions};
+use crate::dependency_graph::DependencyGraph;
+use crate::expression::Expression;
+use crate::function_registry::FunctionRegistry;
+use crate::trace::{self, Trace};
+use crate::value::Value;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Sheet {
     pub cells: Vec<Vec<Cell>>,
+    pub context: Context,
+    /// How the expressions of a resolved sheet were evaluated, see `Sheet::resolve_traced`.
+    pub trace: Trace,
 }
 
 impl Sheet {
-    /// Creates a Sheet with content (2D array of Cells) from a str.
-    pub fn parse_input(input: String) -> Sheet {
-        let rows = input
-            .lines()
-            .enumerate()
-            .map(|(i, line)| {
-                line.split(',')
-                    .enumerate()
-                    .map(|(j, cell)| Cell::parse(i, j, cell))
+    /// Creates a Sheet with content (2D array of Cells) from comma-separated values. Formulas can
+    /// call the functions in the registry.
+    pub fn parse_input(input: String, registry: &FunctionRegistry) -> Result<Sheet, CsvError> {
+        Sheet::parse_csv(&input, &CsvOptions::default(), registry)
+    }
+
+    /// Like `parse_input`, but the cells are read with the given delimiter and quote, see `csv::read`.
+    pub fn parse_csv(
+        input: &str,
+        options: &CsvOptions,
+        registry: &FunctionRegistry,
+    ) -> Result<Sheet, CsvError> {
+        let rows = csv::read(input, options)?
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|field| {
+                        if field.quoted {
+                            Cell::parse_quoted(&field.text, registry)
+                        } else {
+                            Cell::parse(&field.text, registry)
+                        }
+                    })
                     .collect()
             })
             .collect();
 
-        Sheet { cells: rows }
+        Ok(Sheet {
+            cells: rows,
+            context: Context::default(),
+            trace: Trace::default(),
+        })
     }
 
-    /// Processes/resolves all computations to prepare for displaying.
+    /// Processes/resolves all computations to prepare for displaying. Clones are replaced by the cell
+    /// they copy, then expressions are evaluated in the order of the dependency graph, so an
+    /// expression can read the result of another expression.
+    /// Cells that are part of a circular reference resolve to a cycle error.
     pub fn resolve(&self) -> Sheet {
+        self.resolve_with(Trace::default())
+    }
+
+    /// Like `resolve`, but the resolved sheet keeps a trace of how every expression was evaluated,
+    /// see `Sheet::explain`.
+    pub fn resolve_traced(&self) -> Sheet {
+        self.resolve_with(Trace::enabled())
+    }
+
+    fn resolve_with(&self, trace: Trace) -> Sheet {
+        let expanded = self.expand_clones();
+
+        // Literal cells are copied as is, expression cells get filled in as they are evaluated.
         let mut resolved = Sheet {
-            cells: self.cells.iter().map(|_| Vec::new()).collect(),
+            cells: expanded
+                .cells
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|cell| match cell {
+                            Cell::Expression(_) => Cell::Empty,
+                            _ => cell.clone(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            context: self.context.clone(),
+            trace,
         };
 
-        for row in self.cells.iter() {
-            for col in row.iter() {
-                col.resolve(self, &mut resolved);
+        let graph = DependencyGraph::build(&expanded);
+        for component in graph.strongly_connected_components() {
+            if graph.is_circular(&component) {
+                for &(i, j) in component.iter() {
+                    let path = graph
+                        .cycle_path((i, j), &component)
+                        .into_iter()
+                        .map(|(row, col)| CellPos::from_index(row, col))
+                        .collect();
+                    resolved.cells[i][j] = Cell::Error(CellError::cycle(path));
+                }
+            } else {
+                let (i, j) = component[0];
+                resolved.cells[i][j] = match expanded.cells[i][j].resolve(i, j, &resolved) {
+                    Ok(value) => Cell::from(value),
+                    Err(e) => Cell::Error(e),
+                };
+                resolved.trace.finish(i, j);
             }
         }
 
         resolved
     }
+
+    /// The sheet with every clone replaced by the cell it copies, see `Sheet::expand_clone`.
+    fn expand_clones(&self) -> Sheet {
+        Sheet {
+            cells: self
+                .cells
+                .iter()
+                .enumerate()
+                .map(|(i, row)| {
+                    row.iter()
+                        .enumerate()
+                        .map(|(j, cell)| match cell {
+                            Cell::Expression(Expression::Clone(_)) => self.expand_clone(i, j),
+                            _ => cell.clone(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            context: self.context.clone(),
+            trace: Trace::default(),
+        }
+    }
+
+    /// The cell that the clone at (row, col) copies. Clones of clones are followed to the first cell
+    /// that is not a clone. A formula is copied with its references shifted by the distance to that
+    /// cell, so `^` under `=B2 * C2` becomes `=B3 * C3`, any other cell is copied as is.
+    /// Clones that lead back to themselves, e.g. `>` next to `<`, are a cycle error.
+    fn expand_clone(&self, row: usize, col: usize) -> Cell {
+        let mut path = vec![(row, col)];
+        let (mut i, mut j) = (row, col);
+        while let Cell::Expression(Expression::Clone(clone)) = &self.cells[i][j] {
+            let Some(target) = clone.target(i, j) else {
+                return Cell::Error(CellError::reference(format!(
+                    "Cell {} has no neighbour to clone.",
+                    CellPos::from_index(i, j).str
+                )));
+            };
+            let is_cycle = path.contains(&target);
+            path.push(target);
+            if is_cycle {
+                let path = path
+                    .into_iter()
+                    .map(|(row, col)| CellPos::from_index(row, col))
+                    .collect();
+                return Cell::Error(CellError::cycle(path));
+            }
+            match self.cell(target.0, target.1) {
+                Ok(Some(_)) => (i, j) = target,
+                Ok(None) => return Cell::Empty,
+                Err(e) => return Cell::Error(e),
+            }
+        }
+
+        match &self.cells[i][j] {
+            Cell::Expression(Expression::Formula(formula)) => {
+                let rows = row as isize - i as isize;
+                let cols = col as isize - j as isize;
+                match formula.shift(rows, cols) {
+                    Ok(formula) => Cell::Expression(Expression::Formula(formula)),
+                    Err(e) => Cell::Error(e),
+                }
+            }
+            cell => cell.clone(),
+        }
+    }
+
+    /// The number of columns, which is the length of the longest row.
+    pub fn width(&self) -> usize {
+        self.cells.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// The cell at the zero-based (row, col) index. Rows can be shorter than the sheet is wide, the
+    /// cells past their end are empty and give None. A cell outside the sheet is a reference error.
+    pub fn cell(&self, row: usize, col: usize) -> Result<Option<&Cell>, CellError> {
+        match self.cells.get(row) {
+            Some(cells) if col < cells.len() => Ok(Some(&cells[col])),
+            Some(_) if col < self.width() => Ok(None),
+            _ => Err(CellError::reference(format!(
+                "Cell {} is outside the sheet.",
+                CellPos::from_index(row, col).str
+            ))),
+        }
+    }
+
+    /// The value of the cell at the zero-based (row, col) index. Only meaningful on a resolved sheet,
+    /// or for cells that don't hold an expression.
+    /// An error in the cell keeps the cell as its origin.
+    pub fn value(&self, row: usize, col: usize) -> Result<Value, CellError> {
+        match self.cell(row, col)? {
+            Some(cell) => cell
+                .resolve(row, col, self)
+                .map_err(|e| e.with_origin(CellPos::from_index(row, col))),
+            None => Ok(Value::Empty),
+        }
+    }
+}
+
+impl Sheet {
+    /// Explain how the cell at the zero-based (row, col) index of a sheet resolved with
+    /// `resolve_traced` got its value, see `trace::explain`.
+    pub fn explain(&self, row: usize, col: usize) -> String {
+        trace::explain(self, row, col)
+    }
+
+    /// The cells as CSV, see `csv::write`.
+    pub fn write(&self, options: &CsvOptions) -> String {
+        csv::write(&self.cells, options)
+    }
 }
 
 impl fmt::Display for Sheet {
@@ -56,3 +238,243 @@ impl fmt::Display for Sheet {
         write!(f, "{}", out.join("\n"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::cell::Cell;
+    use crate::cell_error::CellError;
+    use crate::cell_pos::CellPos;
+    use crate::function_registry::FunctionRegistry;
+    use crate::sheet::Sheet;
+
+    fn parse_input(input: String) -> Sheet {
+        Sheet::parse_input(input, &FunctionRegistry::default()).unwrap()
+    }
+
+    #[test]
+    fn copies_literal_cells() {
+        let sheet = parse_input("descr, 1.5,\nTotal, , 3".to_string());
+
+        assert_eq!(sheet.resolve(), sheet);
+    }
+
+    #[test]
+    fn stores_results_of_expressions() {
+        let sheet = parse_input("1, =A1 + 1\n=SUM(A1:B1), =A2 * B1".to_string());
+
+        assert_eq!(
+            sheet.resolve().cells,
+            vec![
+                vec![Cell::Number(1.0), Cell::Number(2.0)],
+                vec![Cell::Number(3.0), Cell::Number(6.0)]
+            ]
+        );
+    }
+
+    #[test]
+    fn evaluates_nested_operations() {
+        let sheet = parse_input(
+            "2, =A1 + A1 * 2, =(A1 + 1) / 2, =-A1 ** 2, =-1 + 1, =SUM(A1:A1) * -A1".to_string(),
+        );
+
+        assert_eq!(
+            sheet.resolve().cells[0][1..],
+            [
+                Cell::Number(6.0),
+                Cell::Number(1.5),
+                Cell::Number(-4.0),
+                Cell::Number(0.0),
+                Cell::Number(-4.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn treats_empty_cells_as_zero() {
+        let sheet = parse_input(", =A1 + 1".to_string());
+
+        assert_eq!(sheet.resolve().cells[0][1], Cell::Number(1.0));
+    }
+
+    #[test]
+    fn reads_cells_past_short_rows_as_empty() {
+        let sheet = parse_input("1, 2, 3\n4\n=B2 + 1, =SUM(A2:C2), =COUNTBLANK(B2:C2)".to_string());
+
+        assert_eq!(
+            sheet.resolve().cells[2],
+            [Cell::Number(1.0), Cell::Number(4.0), Cell::Number(2.0)]
+        );
+    }
+
+    #[test]
+    fn resolves_references_outside_the_sheet_to_errors() {
+        let sheet = parse_input("1, 2\n=D1 + 1, \"=SUM(B1, B5)\", =B1".to_string());
+
+        assert_eq!(
+            sheet.resolve().cells[1],
+            [
+                Cell::Error(CellError::reference(
+                    "Cell D1 is outside the sheet.".to_string()
+                )),
+                Cell::Error(CellError::reference(
+                    "Cell B5 is outside the sheet.".to_string()
+                )),
+                Cell::Number(2.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn clones_shift_references() {
+        let sheet = parse_input("2, 3, =A1 * B1\n4, 5, ^\n10, =A3 - 1, <, <".to_string());
+        let resolved = sheet.resolve();
+
+        assert_eq!(resolved.cells[1][2], Cell::Number(20.0));
+        assert_eq!(
+            resolved.cells[2][1..],
+            [Cell::Number(9.0), Cell::Number(8.0), Cell::Number(7.0)]
+        );
+    }
+
+    #[test]
+    fn clones_keep_absolute_references() {
+        let sheet =
+            parse_input("2, 3, =A1 * $B$1 + A$1\n4, 5, ^\n10, =SUM($A$1:A3), <".to_string());
+        let resolved = sheet.resolve();
+
+        assert_eq!(resolved.cells[1][2], Cell::Number(14.0));
+        assert_eq!(
+            resolved.cells[2][1..],
+            [Cell::Number(16.0), Cell::Number(40.0)]
+        );
+    }
+
+    #[test]
+    fn clones_copy_literals() {
+        let sheet = parse_input("text, <, >, 15%, ^\n^, ^, , ^".to_string());
+
+        assert_eq!(
+            sheet.resolve().cells,
+            vec![
+                vec![
+                    Cell::Text("text".to_string()),
+                    Cell::Text("text".to_string()),
+                    Cell::Percent(0.15),
+                    Cell::Percent(0.15),
+                    Cell::Error(CellError::reference(
+                        "Cell E1 has no neighbour to clone.".to_string()
+                    ))
+                ],
+                vec![
+                    Cell::Text("text".to_string()),
+                    Cell::Text("text".to_string()),
+                    Cell::Empty,
+                    Cell::Percent(0.15)
+                ]
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_invalid_clones_to_errors() {
+        let sheet = parse_input(">, <, >, =B2 * 2\n1, 2".to_string());
+
+        assert_eq!(
+            sheet.resolve().cells[0],
+            [
+                Cell::Error(CellError::cycle(vec![
+                    CellPos::from_index(0, 0),
+                    CellPos::from_index(0, 1),
+                    CellPos::from_index(0, 0)
+                ])),
+                Cell::Error(CellError::cycle(vec![
+                    CellPos::from_index(0, 1),
+                    CellPos::from_index(0, 0),
+                    CellPos::from_index(0, 1)
+                ])),
+                Cell::Number(2.0),
+                Cell::Number(4.0)
+            ]
+        );
+
+        let sheet = parse_input("^\n>, =A2 * 2".to_string());
+
+        let shifted_out = CellError::reference(
+            "Reference A2 moves outside the sheet when shifted by 0 rows and -1 columns."
+                .to_string(),
+        );
+        assert_eq!(
+            sheet.resolve().cells,
+            vec![
+                vec![Cell::Error(CellError::reference(
+                    "Cell A1 has no neighbour to clone.".to_string()
+                ))],
+                vec![
+                    Cell::Error(shifted_out.clone()),
+                    Cell::Error(shifted_out.with_origin(CellPos::from_index(1, 0)))
+                ]
+            ]
+        );
+    }
+
+    #[test]
+    fn resolves_text_operands_to_errors() {
+        let sheet = parse_input("total, =A1 + 1".to_string());
+
+        assert_eq!(
+            sheet.resolve().cells[0][1],
+            Cell::Error(CellError::value(
+                "Cell A1 contains text 'total' instead of a number.".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn keeps_function_arguments_in_one_cell() {
+        let sheet = parse_input(
+            "1, 2, \"=SUM(A1:B1, 10, MAX(A1, B1))\", \"=\"\"a, b\"\" & A1\", (x, y)".to_string(),
+        );
+
+        assert_eq!(
+            sheet.resolve().cells[0][2..],
+            [
+                Cell::Number(15.0),
+                Cell::Text("a, b1".to_string()),
+                Cell::Text("(x".to_string()),
+                Cell::Text("y)".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn flattens_function_arguments() {
+        let sheet = parse_input(
+            "4, text, , TRUE\n\"=SUM(A1:D1, A1)\", \"=COUNT(A1:D1, 1, B1)\", \"=AVG(A1, TRUE)\", \"=MIN(A1:D1, -1)\""
+                .to_string(),
+        );
+
+        assert_eq!(
+            sheet.resolve().cells[1],
+            [
+                Cell::Number(8.0),
+                Cell::Number(2.0),
+                Cell::Number(2.5),
+                Cell::Number(-1.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn propagates_errors_of_referenced_cells() {
+        let sheet = parse_input("=A1 + 1, =A1 * 2, =SUM(A1:B1)".to_string());
+        let resolved = sheet.resolve();
+
+        let Cell::Error(error) = &resolved.cells[0][0] else {
+            panic!("A1 should be an error");
+        };
+        assert_eq!(error.origin, None);
+        let propagated = Cell::Error(error.clone().with_origin(CellPos::from_index(0, 0)));
+        assert_eq!(resolved.cells[0][1], propagated);
+        assert_eq!(resolved.cells[0][2], propagated);
+    }
+}
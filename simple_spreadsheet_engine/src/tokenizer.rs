@@ -0,0 +1,302 @@
+use std::fmt;
+
+use crate::parser::ParseError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    Ampersand,
+    Colon,
+    Comma,
+    DoubleStar,
+    Equal,
+    GreaterThan,
+    GreaterThanOrEqual,
+    LeftParen,
+    LessThan,
+    LessThanOrEqual,
+    Minus,
+    NotEqual,
+    Number(f64),
+    Percent,
+    Plus,
+    RightParen,
+    Slash,
+    Star,
+    /// A string literal, without the surrounding quotes.
+    Text(String),
+    /// A function name or cell reference, e.g. SUM or A1.
+    Word(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Ampersand => write!(f, "&"),
+            Token::Colon => write!(f, ":"),
+            Token::Comma => write!(f, ","),
+            Token::DoubleStar => write!(f, "**"),
+            Token::Equal => write!(f, "="),
+            Token::GreaterThan => write!(f, ">"),
+            Token::GreaterThanOrEqual => write!(f, ">="),
+            Token::LeftParen => write!(f, "("),
+            Token::LessThan => write!(f, "<"),
+            Token::LessThanOrEqual => write!(f, "<="),
+            Token::Minus => write!(f, "-"),
+            Token::NotEqual => write!(f, "<>"),
+            Token::Number(n) => write!(f, "{}", n),
+            Token::Percent => write!(f, "%"),
+            Token::Plus => write!(f, "+"),
+            Token::RightParen => write!(f, ")"),
+            Token::Slash => write!(f, "/"),
+            Token::Star => write!(f, "*"),
+            Token::Text(t) => write!(f, "\"{}\"", t.replace('"', "\"\"")),
+            Token::Word(w) => write!(f, "{}", w),
+        }
+    }
+}
+
+/// Split the input into tokens, each paired with the column (zero-based character offset) it starts at.
+pub fn tokenize(input: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        let token = match c {
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '0'..='9' | '.' => {
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let number: String = chars[start..i].iter().collect();
+                match number.parse::<f64>() {
+                    Ok(n) => Token::Number(n),
+                    Err(_) => {
+                        return Err(ParseError::new(
+                            format!("Invalid number '{}'.", number),
+                            start,
+                        ))
+                    }
+                }
+            }
+            '"' => {
+                // A quote inside a string literal is escaped by doubling it.
+                let mut text = String::new();
+                i += 1;
+                loop {
+                    match (chars.get(i), chars.get(i + 1)) {
+                        (Some('"'), Some('"')) => {
+                            text.push('"');
+                            i += 2;
+                        }
+                        (Some('"'), _) => {
+                            i += 1;
+                            break;
+                        }
+                        (Some(c), _) => {
+                            text.push(*c);
+                            i += 1;
+                        }
+                        (None, _) => {
+                            return Err(ParseError::new(
+                                "Unterminated string literal.".to_string(),
+                                start,
+                            ))
+                        }
+                    }
+                }
+                Token::Text(text)
+            }
+            _ if c.is_ascii_alphabetic() || c == '$' => {
+                while i < chars.len()
+                    && (chars[i].is_ascii_alphanumeric() || chars[i] == '.' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                Token::Word(chars[start..i].iter().collect())
+            }
+            _ => {
+                let (token, length) = match (c, chars.get(i + 1)) {
+                    ('*', Some('*')) => (Token::DoubleStar, 2),
+                    ('<', Some('=')) => (Token::LessThanOrEqual, 2),
+                    ('<', Some('>')) => (Token::NotEqual, 2),
+                    ('>', Some('=')) => (Token::GreaterThanOrEqual, 2),
+                    ('&', _) => (Token::Ampersand, 1),
+                    (':', _) => (Token::Colon, 1),
+                    (',', _) => (Token::Comma, 1),
+                    ('=', _) => (Token::Equal, 1),
+                    ('>', _) => (Token::GreaterThan, 1),
+                    ('(', _) => (Token::LeftParen, 1),
+                    ('<', _) => (Token::LessThan, 1),
+                    ('-', _) => (Token::Minus, 1),
+                    ('%', _) => (Token::Percent, 1),
+                    ('+', _) => (Token::Plus, 1),
+                    (')', _) => (Token::RightParen, 1),
+                    ('/', _) => (Token::Slash, 1),
+                    ('*', _) => (Token::Star, 1),
+                    _ => {
+                        return Err(ParseError::new(
+                            format!("Unexpected character '{}'.", c),
+                            start,
+                        ))
+                    }
+                };
+                i += length;
+                token
+            }
+        };
+        tokens.push((start, token));
+    }
+
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::ParseError;
+    use crate::tokenizer::{tokenize, Token};
+
+    #[test]
+    fn can_tokenize_arithmetic() {
+        assert_eq!(
+            tokenize("1.23 + A1").unwrap(),
+            vec![
+                (0, Token::Number(1.23)),
+                (5, Token::Plus),
+                (7, Token::Word("A1".to_string()))
+            ]
+        );
+        assert_eq!(
+            tokenize("-(B2**2)/C3*4").unwrap(),
+            vec![
+                (0, Token::Minus),
+                (1, Token::LeftParen),
+                (2, Token::Word("B2".to_string())),
+                (4, Token::DoubleStar),
+                (6, Token::Number(2.0)),
+                (7, Token::RightParen),
+                (8, Token::Slash),
+                (9, Token::Word("C3".to_string())),
+                (11, Token::Star),
+                (12, Token::Number(4.0))
+            ]
+        );
+        assert_eq!(
+            tokenize("A1*15%").unwrap(),
+            vec![
+                (0, Token::Word("A1".to_string())),
+                (2, Token::Star),
+                (3, Token::Number(15.0)),
+                (5, Token::Percent)
+            ]
+        );
+    }
+
+    #[test]
+    fn can_tokenize_comparisons() {
+        assert_eq!(
+            tokenize("1=2>3>=4<5<=6<>7").unwrap(),
+            vec![
+                (0, Token::Number(1.0)),
+                (1, Token::Equal),
+                (2, Token::Number(2.0)),
+                (3, Token::GreaterThan),
+                (4, Token::Number(3.0)),
+                (5, Token::GreaterThanOrEqual),
+                (7, Token::Number(4.0)),
+                (8, Token::LessThan),
+                (9, Token::Number(5.0)),
+                (10, Token::LessThanOrEqual),
+                (12, Token::Number(6.0)),
+                (13, Token::NotEqual),
+                (15, Token::Number(7.0))
+            ]
+        );
+        assert_eq!(
+            tokenize("A1 < > B1").unwrap(),
+            vec![
+                (0, Token::Word("A1".to_string())),
+                (3, Token::LessThan),
+                (5, Token::GreaterThan),
+                (7, Token::Word("B1".to_string()))
+            ]
+        );
+    }
+
+    #[test]
+    fn can_tokenize_text() {
+        assert_eq!(
+            tokenize("\"Total: \" & D5").unwrap(),
+            vec![
+                (0, Token::Text("Total: ".to_string())),
+                (10, Token::Ampersand),
+                (12, Token::Word("D5".to_string()))
+            ]
+        );
+        assert_eq!(
+            tokenize("\"say \"\"hi\"\"\"&\"\"").unwrap(),
+            vec![
+                (0, Token::Text("say \"hi\"".to_string())),
+                (12, Token::Ampersand),
+                (13, Token::Text(String::new()))
+            ]
+        );
+    }
+
+    #[test]
+    fn can_tokenize_functions() {
+        assert_eq!(
+            tokenize("SUM(A1:B2, 3)").unwrap(),
+            vec![
+                (0, Token::Word("SUM".to_string())),
+                (3, Token::LeftParen),
+                (4, Token::Word("A1".to_string())),
+                (6, Token::Colon),
+                (7, Token::Word("B2".to_string())),
+                (9, Token::Comma),
+                (11, Token::Number(3.0)),
+                (12, Token::RightParen)
+            ]
+        );
+        assert_eq!(
+            tokenize("SUM($A$1:B$2)").unwrap(),
+            vec![
+                (0, Token::Word("SUM".to_string())),
+                (3, Token::LeftParen),
+                (4, Token::Word("$A$1".to_string())),
+                (8, Token::Colon),
+                (9, Token::Word("B$2".to_string())),
+                (12, Token::RightParen)
+            ]
+        );
+    }
+
+    #[test]
+    fn handles_invalid_input() {
+        assert_eq!(tokenize(""), Ok(vec![]));
+        assert_eq!(
+            tokenize("1 ? 2"),
+            Err(ParseError::new("Unexpected character '?'.".to_string(), 2))
+        );
+        assert_eq!(
+            tokenize("1.2.3"),
+            Err(ParseError::new("Invalid number '1.2.3'.".to_string(), 0))
+        );
+        assert_eq!(
+            tokenize("1 & \"oops"),
+            Err(ParseError::new(
+                "Unterminated string literal.".to_string(),
+                4
+            ))
+        );
+        assert_eq!(
+            tokenize("A1 + #ERROR#"),
+            Err(ParseError::new("Unexpected character '#'.".to_string(), 5))
+        );
+    }
+}
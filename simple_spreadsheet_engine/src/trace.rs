@@ -0,0 +1,311 @@
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+    cell_error::CellError, cell_pos::CellPos, dependency_graph::Index, function_registry::Argument,
+    sheet::Sheet, value::Value,
+};
+
+/// The evaluation of a formula: the formula, the values that were substituted for its operands and
+/// the result, with the steps of the formulas among its operands.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    /// The formula as written, e.g. A1 * (B1 + 1).
+    pub formula: String,
+    /// The values of the operands in the order they were evaluated, e.g. the values of A1 and
+    /// B1 + 1. Only the arguments that a function reads are evaluated, see `Arguments::get`.
+    pub operands: Vec<Argument>,
+    pub result: Result<Value, CellError>,
+    /// The steps of the operands that are formulas or cell references, e.g. of A1 and B1 + 1.
+    pub steps: Vec<Step>,
+}
+
+/// Steps that are being recorded: the operands and steps of a formula that is being evaluated.
+#[derive(Debug, Default, PartialEq)]
+struct Frame {
+    operands: Vec<Argument>,
+    steps: Vec<Step>,
+}
+
+/// Records the evaluation of every expression cell of a sheet, see `Sheet::resolve_traced`. A
+/// trace that isn't enabled records nothing.
+#[derive(Debug, Default, PartialEq)]
+pub struct Trace {
+    enabled: bool,
+    /// The formulas that are being evaluated, innermost last.
+    frames: RefCell<Vec<Frame>>,
+    /// The step of the formula that was evaluated last, until it is kept by `finish`.
+    last: RefCell<Option<Step>>,
+    /// The step of the formula the cell at the zero-based (row, col) index holds.
+    cells: RefCell<BTreeMap<Index, Step>>,
+}
+
+impl Trace {
+    /// A trace that records the evaluation.
+    pub(crate) fn enabled() -> Self {
+        Trace {
+            enabled: true,
+            ..Trace::default()
+        }
+    }
+
+    /// The step of the expression in the cell at the zero-based (row, col) index, None if the cell
+    /// holds no expression, is part of a circular reference or the trace isn't enabled.
+    pub fn step(&self, row: usize, col: usize) -> Option<Step> {
+        self.cells.borrow().get(&(row, col)).cloned()
+    }
+
+    /// Start recording the evaluation of a formula.
+    pub(crate) fn enter(&self) {
+        if self.enabled {
+            self.frames.borrow_mut().push(Frame::default());
+        }
+    }
+
+    /// Record the values of operands of the formula that is being evaluated.
+    pub(crate) fn operands(&self, operands: impl FnOnce() -> Vec<Argument>) {
+        if let Some(frame) = self.frames.borrow_mut().last_mut() {
+            frame.operands.extend(operands());
+        }
+    }
+
+    /// Finish recording the formula that is being evaluated. Its step becomes a step of the formula
+    /// around it, if there is one.
+    pub(crate) fn exit(&self, formula: impl FnOnce() -> String, result: &Result<Value, CellError>) {
+        let mut frames = self.frames.borrow_mut();
+        let Some(frame) = frames.pop() else {
+            return;
+        };
+        let step = Step {
+            formula: formula(),
+            operands: frame.operands,
+            result: result.clone(),
+            steps: frame.steps,
+        };
+        match frames.last_mut() {
+            Some(parent) => parent.steps.push(step),
+            None => *self.last.borrow_mut() = Some(step),
+        }
+    }
+
+    /// Keep the step of the formula that was evaluated last as the step of the cell at the
+    /// zero-based (row, col) index.
+    pub(crate) fn finish(&self, row: usize, col: usize) {
+        if let Some(step) = self.last.take() {
+            self.cells.borrow_mut().insert((row, col), step);
+        }
+    }
+}
+
+/// Explain how the cell at the zero-based (row, col) index of a sheet resolved with
+/// `Sheet::resolve_traced` got its value, as a tree of the steps of its formula, e.g.
+///
+/// ```text
+/// C1: =A1 * B1 = 12 (from 3, 4)
+/// ├─ A1 = 3
+/// └─ B1 = 4
+///    └─ =A1 + 1 = 4 (from 3, 1)
+///       └─ A1 = 3
+/// ```
+///
+/// A reference to a cell with a formula is followed by the steps of that formula, only the first
+/// time the cell is referenced.
+pub(crate) fn explain(resolved: &Sheet, row: usize, col: usize) -> String {
+    let name = CellPos::from_index(row, col).str;
+    let mut explained = BTreeSet::from([(row, col)]);
+    let mut lines = Vec::new();
+    match resolved.trace.step(row, col) {
+        Some(step) => {
+            lines.push(format!("{}: ={}", name, describe(&step)));
+            explain_steps(resolved, &step.steps, "", &mut explained, &mut lines);
+        }
+        None => match resolved.cell(row, col) {
+            Ok(Some(cell)) => lines.push(format!("{}: {}", name, cell)),
+            Ok(None) => lines.push(format!("{}:", name)),
+            Err(e) => lines.push(format!("{}: {}", name, e)),
+        },
+    }
+    lines.join("\n")
+}
+
+fn explain_steps(
+    resolved: &Sheet,
+    steps: &[Step],
+    indent: &str,
+    explained: &mut BTreeSet<Index>,
+    lines: &mut Vec<String>,
+) {
+    for (i, step) in steps.iter().enumerate() {
+        let is_last = i == steps.len() - 1;
+        let (branch, nested) = if is_last {
+            ("└─ ", "   ")
+        } else {
+            ("├─ ", "│  ")
+        };
+        lines.push(format!("{}{}{}", indent, branch, describe(step)));
+        let indent = format!("{}{}", indent, nested);
+        explain_steps(resolved, &step.steps, &indent, explained, lines);
+
+        // A cell reference has no steps of its own, the formula in the cell explains its value.
+        if let Ok(pos) = CellPos::parse(&step.formula) {
+            let (row, col) = pos.index();
+            if let Some(cell_step) = resolved.trace.step(row, col) {
+                if explained.insert((row, col)) {
+                    lines.push(format!("{}└─ ={}", indent, describe(&cell_step)));
+                    let indent = format!("{}   ", indent);
+                    explain_steps(resolved, &cell_step.steps, &indent, explained, lines);
+                }
+            }
+        }
+    }
+}
+
+/// The formula of the step with its result and operands, e.g. A1 * B1 = 12 (from 3, 4).
+fn describe(step: &Step) -> String {
+    let result = match &step.result {
+        Ok(value) => value.to_string(),
+        Err(e) => e.to_string(),
+    };
+    let mut description = format!("{} = {}", step.formula, result);
+    if !step.operands.is_empty() {
+        let operands: Vec<String> = step.operands.iter().map(|arg| arg.to_string()).collect();
+        description.push_str(&format!(" (from {})", operands.join(", ")));
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cell_error::CellError;
+    use crate::function_registry::{Argument, FunctionRegistry};
+    use crate::sheet::Sheet;
+    use crate::trace::Step;
+    use crate::value::Value;
+
+    fn resolve_traced(input: &str) -> Sheet {
+        Sheet::parse_input(input.to_string(), &FunctionRegistry::default())
+            .unwrap()
+            .resolve_traced()
+    }
+
+    fn step(formula: &str, operands: Vec<Argument>, result: Value, steps: Vec<Step>) -> Step {
+        Step {
+            formula: formula.to_string(),
+            operands,
+            result: Ok(result),
+            steps,
+        }
+    }
+
+    fn reference(formula: &str, value: f64) -> Step {
+        step(formula, vec![], Value::Number(value), vec![])
+    }
+
+    fn number(n: f64) -> Argument {
+        Argument::Value(Value::Number(n))
+    }
+
+    #[test]
+    fn records_steps_of_formulas() {
+        let resolved = resolve_traced("3, =A1 + 1, =A1 * -B1, \"=SUM(A1:B1, 2 * A1)\"");
+
+        assert_eq!(resolved.trace.step(0, 0), None);
+        assert_eq!(
+            resolved.trace.step(0, 1),
+            Some(step(
+                "A1 + 1",
+                vec![number(3.0), number(1.0)],
+                Value::Number(4.0),
+                vec![reference("A1", 3.0)]
+            ))
+        );
+        assert_eq!(
+            resolved.trace.step(0, 2),
+            Some(step(
+                "A1 * -B1",
+                vec![number(3.0), number(-4.0)],
+                Value::Number(-12.0),
+                vec![
+                    reference("A1", 3.0),
+                    step(
+                        "-B1",
+                        vec![number(4.0)],
+                        Value::Number(-4.0),
+                        vec![reference("B1", 4.0)]
+                    )
+                ]
+            ))
+        );
+        assert_eq!(
+            resolved.trace.step(0, 3),
+            Some(step(
+                "SUM(A1:B1, 2 * A1)",
+                vec![
+                    Argument::Reference(vec![vec![Value::Number(3.0), Value::Number(4.0)]]),
+                    number(6.0)
+                ],
+                Value::Number(13.0),
+                vec![step(
+                    "2 * A1",
+                    vec![number(2.0), number(3.0)],
+                    Value::Number(6.0),
+                    vec![reference("A1", 3.0)]
+                )]
+            ))
+        );
+    }
+
+    #[test]
+    fn records_errors_and_only_evaluated_arguments() {
+        let resolved = resolve_traced("\"=IF(TRUE, 1, 1 / 0)\", =1 / 0");
+
+        assert_eq!(
+            resolved.trace.step(0, 0),
+            Some(step(
+                "IF(TRUE, 1, 1 / 0)",
+                vec![Argument::Value(Value::Boolean(true)), number(1.0)],
+                Value::Number(1.0),
+                vec![]
+            ))
+        );
+        assert_eq!(
+            resolved.trace.step(0, 1),
+            Some(Step {
+                formula: "1 / 0".to_string(),
+                operands: vec![number(1.0), number(0.0)],
+                result: Err(CellError::division_by_zero("Cannot divide 1 by zero.")),
+                steps: vec![]
+            })
+        );
+    }
+
+    #[test]
+    fn records_nothing_unless_enabled() {
+        let sheet = Sheet::parse_input("1, =A1 + 1".to_string(), &FunctionRegistry::default());
+        assert_eq!(sheet.unwrap().resolve().trace.step(0, 1), None);
+    }
+
+    #[test]
+    fn explains_cells_as_tree() {
+        let resolved = resolve_traced("3, =A1 + 1, \"=A1 * B1 + SUM(B1:B1, A1)\", text");
+
+        assert_eq!(
+            resolved.explain(0, 2),
+            [
+                "C1: =A1 * B1 + SUM(B1:B1, A1) = 19 (from 12, 7)",
+                "├─ A1 * B1 = 12 (from 3, 4)",
+                "│  ├─ A1 = 3",
+                "│  └─ B1 = 4",
+                "│     └─ =A1 + 1 = 4 (from 3, 1)",
+                "│        └─ A1 = 3",
+                "└─ SUM(B1:B1, A1) = 7 (from [4], [3])",
+            ]
+            .join("\n")
+        );
+        assert_eq!(resolved.explain(0, 3), "D1: text");
+        assert_eq!(
+            resolved.explain(0, 9),
+            "J1: #REF!: Cell J1 is outside the sheet."
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::cell::Cell;
+
+/// The value a cell resolves to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Boolean(bool),
+    /// A date as serial number, see `date`. Behaves like a number in calculations.
+    Date(f64),
+    Empty,
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    /// The number of a number or date.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Date(n) | Value::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// Compare two values the way spreadsheets do: numbers sort before text and text before
+    /// booleans, text is compared case-insensitively and an empty value equals 0, "" or FALSE
+    /// depending on what it is compared with.
+    pub fn compare(&self, other: &Value) -> Ordering {
+        fn type_order(value: &Value) -> u8 {
+            match value {
+                Value::Date(_) | Value::Empty | Value::Number(_) => 0,
+                Value::Text(_) => 1,
+                Value::Boolean(_) => 2,
+            }
+        }
+
+        match (self, other) {
+            (Value::Empty, Value::Empty) => Ordering::Equal,
+            (Value::Empty, _) => other.blank().compare(other),
+            (_, Value::Empty) => self.compare(&self.blank()),
+            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+            (Value::Text(a), Value::Text(b)) => a.to_lowercase().cmp(&b.to_lowercase()),
+            _ => match (self.as_number(), other.as_number()) {
+                (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+                _ => type_order(self).cmp(&type_order(other)),
+            },
+        }
+    }
+
+    /// What an empty cell counts as when it is compared with a value of this type.
+    fn blank(&self) -> Value {
+        match self {
+            Value::Boolean(_) => Value::Boolean(false),
+            Value::Date(_) | Value::Empty | Value::Number(_) => Value::Number(0.0),
+            Value::Text(_) => Value::Text(String::new()),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Cell::from(self.clone()))
+    }
+}
+
+impl From<Value> for Cell {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Boolean(b) => Cell::Boolean(b),
+            Value::Date(n) => Cell::Date(n),
+            Value::Empty => Cell::Empty,
+            Value::Number(n) => Cell::Number(n),
+            Value::Text(t) => Cell::Text(t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::value::Value;
+
+    fn text(t: &str) -> Value {
+        Value::Text(t.to_string())
+    }
+
+    #[test]
+    fn compares_values_of_the_same_type() {
+        assert_eq!(
+            Value::Number(1.0).compare(&Value::Number(2.0)),
+            Ordering::Less
+        );
+        assert_eq!(
+            Value::Number(2.0).compare(&Value::Number(2.0)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Value::Boolean(true).compare(&Value::Boolean(false)),
+            Ordering::Greater
+        );
+        assert_eq!(text("apple").compare(&text("banana")), Ordering::Less);
+    }
+
+    #[test]
+    fn compares_dates_as_numbers() {
+        assert_eq!(
+            Value::Date(42144.0).compare(&Value::Number(42144.0)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Value::Date(42144.0).compare(&Value::Date(42145.0)),
+            Ordering::Less
+        );
+        assert_eq!(Value::Date(1.0).compare(&text("1")), Ordering::Less);
+    }
+
+    #[test]
+    fn compares_text_case_insensitively() {
+        assert_eq!(text("Coffee").compare(&text("COFFEE")), Ordering::Equal);
+        assert_eq!(text("b").compare(&text("A")), Ordering::Greater);
+    }
+
+    #[test]
+    fn sorts_numbers_before_text_before_booleans() {
+        assert_eq!(Value::Number(100.0).compare(&text("1")), Ordering::Less);
+        assert_eq!(text("zzz").compare(&Value::Boolean(false)), Ordering::Less);
+        assert_eq!(
+            Value::Boolean(false).compare(&Value::Number(1e10)),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn compares_empty_as_blank_of_other_type() {
+        assert_eq!(Value::Empty.compare(&Value::Empty), Ordering::Equal);
+        assert_eq!(Value::Empty.compare(&Value::Number(0.0)), Ordering::Equal);
+        assert_eq!(
+            Value::Empty.compare(&Value::Number(-1.0)),
+            Ordering::Greater
+        );
+        assert_eq!(Value::Empty.compare(&text("")), Ordering::Equal);
+        assert_eq!(Value::Empty.compare(&text("a")), Ordering::Less);
+        assert_eq!(
+            Value::Boolean(false).compare(&Value::Empty),
+            Ordering::Equal
+        );
+    }
+}
@@ -1,4 +1,11 @@
 use std::fs;
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use simple_spreadsheet_engine::{
+    numbers, Argument, Arity, CellError, Context, CsvLayout, CsvOptions, FunctionRegistry, Sheet,
+    SpreadsheetFunction, Value,
+};
 
 // TODO: parametrized tests instead of asserting each variant manually.
 // Could do it through macros or with a package https://crates.io/crates/rstest
@@ -7,11 +14,26 @@ fn compare_files(name: &str) {
     let input = fs::read_to_string("examples/".to_owned() + name + ".csv").unwrap();
     let expected_output = fs::read_to_string("examples/".to_owned() + name + ".out.csv").unwrap();
     assert_eq!(
-        simple_spreadsheet_engine::run(input),
+        simple_spreadsheet_engine::run(input).unwrap(),
         expected_output.trim()
     );
 }
 
+/// Compare the evaluated sheet written with the layout to the expected output.
+fn compare_written(name: &str, layout: CsvLayout) {
+    let input = fs::read_to_string("examples/".to_owned() + name + ".csv").unwrap();
+    let expected_output = fs::read_to_string("examples/".to_owned() + name + ".out.csv").unwrap();
+    let options = CsvOptions {
+        layout,
+        ..CsvOptions::default()
+    };
+    let sheet = Sheet::parse_csv(&input, &options, &FunctionRegistry::default()).unwrap();
+    assert_eq!(
+        sheet.resolve().write(&options),
+        expected_output.trim_end_matches('\n')
+    );
+}
+
 #[test]
 fn arithmetic_with_literals() {
     compare_files("arithmetic_with_literals")
@@ -22,6 +44,46 @@ fn arithmetic_with_references() {
     compare_files("arithmetic_with_references");
 }
 
+#[test]
+fn quoted_cells() {
+    compare_files("quoted_cells");
+}
+
+#[test]
+fn ragged_references() {
+    compare_files("ragged_references");
+}
+
+#[test]
+fn circular_references() {
+    compare_files("circular_references");
+}
+
+#[test]
+fn comparisons() {
+    compare_files("comparisons");
+}
+
+#[test]
+fn conditionals() {
+    compare_files("conditionals");
+}
+
+#[test]
+fn dates() {
+    compare_files("dates");
+}
+
+#[test]
+fn error_types() {
+    compare_files("error_types");
+}
+
+#[test]
+fn expenses() {
+    compare_files("expenses");
+}
+
 #[test]
 fn functions_on_rows() {
     compare_files("functions_on_rows");
@@ -37,22 +99,215 @@ fn functions_on_blocks() {
     compare_files("functions_on_blocks");
 }
 
+#[test]
+fn function_arguments() {
+    compare_files("function_arguments");
+}
+
 #[test]
 fn statistics() {
     compare_files("statistics");
 }
 
-// #[test]
-// fn countdown() {
-//     compare_files("countdown");
-// }
+#[test]
+fn statistics_counts() {
+    compare_files("statistics_counts");
+}
+
+#[test]
+fn statistics_order() {
+    compare_files("statistics_order");
+}
+
+#[test]
+fn statistics_pairs() {
+    compare_files("statistics_pairs");
+}
+
+#[test]
+fn statistics_spread() {
+    compare_files("statistics_spread");
+}
+
+#[test]
+fn text_concatenation() {
+    compare_files("text_concatenation");
+}
+
+#[test]
+fn text_functions() {
+    compare_files("text_functions");
+}
+
+#[test]
+fn countdown() {
+    compare_written("countdown", CsvLayout::Canonical);
+}
+
+#[test]
+fn shopping() {
+    compare_written("shopping", CsvLayout::Aligned);
+}
+
+#[test]
+fn error() {
+    compare_written("error", CsvLayout::Aligned);
+}
+
+#[test]
+fn loans() {
+    compare_files("loans");
+}
+
+#[test]
+fn lookups() {
+    compare_files("lookups");
+}
+
+#[test]
+fn math() {
+    compare_files("math");
+}
+
+#[test]
+fn percentages() {
+    compare_files("percentages");
+}
+
+/// Converts amounts in euro to dollars at a fixed rate, summing all its arguments.
+struct ToUsd;
+
+impl SpreadsheetFunction for ToUsd {
+    fn name(&self) -> &str {
+        "TOUSD"
+    }
+
+    fn arity(&self) -> Arity {
+        Arity::at_least(1)
+    }
+
+    fn evaluate(&self, args: &[Argument], _: &Context) -> Result<Value, CellError> {
+        let total: f64 = numbers(args)?.iter().sum();
+        Ok(Value::Number(total * 1.5))
+    }
+}
+
+#[test]
+fn custom_functions() {
+    let mut registry = FunctionRegistry::default();
+    registry.register(ToUsd);
+
+    assert_eq!(
+        simple_spreadsheet_engine::run_with(
+            "10, 20, =TOUSD(A1:B1), \"=TOUSD(A1, 2) + SUM(A1:B1)\", =TOUSD()".to_string(),
+            &registry,
+            &Context::default()
+        )
+        .unwrap(),
+        "10, 20, 45, 48, #ERROR#: Function 'TOUSD' expects at least 1 argument but got 0. (column 0)"
+    );
+    assert_eq!(
+        simple_spreadsheet_engine::run("=TOUSD(1)".to_string()).unwrap(),
+        "#NAME?: Function 'TOUSD' not supported."
+    );
+}
+
+/// Run the command line binary with the arguments, writing the input to its standard input.
+fn command_line(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_simple_spreadsheet_engine"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn command_line_evaluates_files() {
+    let output = command_line(&["--format", "aligned", "examples/shopping.csv"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        fs::read_to_string("examples/shopping.out.csv").unwrap()
+    );
+}
+
+#[test]
+fn command_line_reads_standard_input() {
+    let output = command_line(
+        &["-d", ";", "--output-delimiter", ","],
+        "1;2;=SUM(A1:B1, 4)\n",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "1,2,7\n");
+
+    let output = command_line(&["--formulas", "-"], "1, =A1 * 2\n^, ^");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "1,=A1 * 2\n^,^\n"
+    );
+}
+
+#[test]
+fn command_line_writes_files() {
+    let path = std::env::temp_dir().join("simple_spreadsheet_engine_countdown.out.csv");
+    let output = command_line(
+        &["examples/countdown.csv", "-o", path.to_str().unwrap()],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        fs::read_to_string("examples/countdown.out.csv").unwrap()
+    );
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn command_line_reports_errors_in_exit_status() {
+    let output = command_line(&[], "1, =A1 / 0");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "1,#DIV/0!: Cannot divide 1 by zero.\n"
+    );
+
+    let output = command_line(&[], "\"unclosed");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Could not read the input: Quoted cell is not closed. (line 1, column 1)\n"
+    );
 
-// #[test]
-// fn shopping() {
-//     compare_files("shopping");
-// }
+    let output = command_line(&["--format", "pretty"], "");
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("Unknown format 'pretty'."));
+}
 
-// #[test]
-// fn error() {
-//     compare_files("error");
-// }
+#[test]
+fn command_line_explains_cells() {
+    let output = command_line(&["--explain", "C1"], "2, =A1 * 3, =B1 + A1\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "C1: =B1 + A1 = 8 (from 6, 2)\n├─ B1 = 6\n│  └─ =A1 * 3 = 6 (from 2, 3)\n│     └─ A1 = 2\n└─ A1 = 2\n"
+    );
+
+    let output = command_line(&["--explain", "Z9"], "1");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "Cell Z9 is outside the sheet.\n"
+    );
+}